@@ -0,0 +1,102 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use std::rc::Rc;
+
+use gl::types::GLuint;
+use glam::{Vec2, Vec4};
+
+use crate::texture::Texture;
+
+// Sky drawn behind everything else, it turns with the camera but never moves closer. Textures are shared,
+// they stay alive as long as the skybox does.
+#[derive(Clone)]
+pub struct Skybox {
+    // Texture per cube face, or a single panorama
+    textures: Vec<Rc<Texture>>,
+}
+
+impl Skybox {
+    // Faces in +x, -x, +y, -y, +z, -z order, each as seen from inside the cube. Camera looks down -z, so -z is
+    // the front face. Side faces are upright, bottom edge of the top face and top edge of the bottom face
+    // touch the front face.
+    pub fn from_cube(faces: [&Rc<Texture>; 6]) -> Skybox {
+        Skybox { textures: faces.iter().map(|face| Rc::clone(face)).collect() }
+    }
+
+    // Equirectangular image, 360 degrees around and 180 degrees from bottom to top. Center of the image is
+    // the front, looking down -z.
+    pub fn from_panorama(texture: &Rc<Texture>) -> Skybox {
+        Skybox { textures: vec![texture.clone()] }
+    }
+
+    pub(crate) fn get_textures(&self) -> &[Rc<Texture>] {
+        &self.textures
+    }
+
+    // Texture of every cube face, a panorama covers all of them
+    pub(crate) fn get_texture_ids(&self) -> [GLuint; 6] {
+        std::array::from_fn(|face| self.textures[face % self.textures.len()].texture_id())
+    }
+
+    pub(crate) fn is_panorama(&self) -> bool {
+        self.textures.len() == 1
+    }
+}
+
+// Full screen image drawn behind the 3D scene, over the skybox. One texture pixel is one framebuffer pixel
+// and the texture repeats in both directions. Layers scrolling at different speeds give parallax, like in
+// fixed camera rooms with clouds or a view out of a moving train.
+#[derive(Clone)]
+pub struct BackgroundLayer {
+    texture: Rc<Texture>,
+    texture_size: Vec2,
+    // Moves the layer, in framebuffer pixels, y goes up
+    pub offset: Vec2,
+    // Pixels per second added to the offset by update
+    pub scroll_speed: Vec2,
+    pub tint: Vec4,
+}
+
+impl BackgroundLayer {
+    pub fn new(texture: &Rc<Texture>) -> BackgroundLayer {
+        BackgroundLayer {
+            texture: texture.clone(),
+            texture_size: Vec2::new(texture.width().max(1) as f32, texture.height().max(1) as f32),
+            offset: Vec2::ZERO,
+            scroll_speed: Vec2::ZERO,
+            tint: Vec4::ONE
+        }
+    }
+
+    // Delta time in seconds, same as Scene::on_update gets. Offset is kept within one texture size, the
+    // layer repeats anyway.
+    pub fn update(&mut self, delta_time: f64) {
+        self.offset = (self.offset + self.scroll_speed * delta_time as f32).rem_euclid(self.texture_size);
+    }
+
+    pub(crate) fn get_texture(&self) -> &Rc<Texture> {
+        &self.texture
+    }
+
+    pub(crate) fn get_texture_size(&self) -> &Vec2 {
+        &self.texture_size
+    }
+}
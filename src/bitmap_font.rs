@@ -0,0 +1,405 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use std::collections::HashMap;
+
+use glam::{Vec2, Vec4};
+
+use crate::error::Error;
+use crate::sprite::Rect;
+use crate::texture::Texture;
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum TextAlignment {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct TextStyle {
+    pub color: Vec4,
+    // Every line is aligned to the text position, it's the left edge, the center or the right edge of the line
+    pub alignment: TextAlignment,
+    // Lines longer than this break between words, words that don't fit on a line of their own are split.
+    // In framebuffer pixels.
+    pub wrap_width: Option<f32>,
+    // Same as Sprite layer
+    pub layer: i32,
+}
+
+impl TextStyle {
+    pub fn new(color: Vec4) -> TextStyle {
+        TextStyle { color, alignment: TextAlignment::Left, wrap_width: None, layer: 0 }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+struct Glyph {
+    source_rect: Rect,
+    // From the pen position at the top of the line to the top left corner of the glyph, y goes down
+    offset: Vec2,
+    advance: f32,
+}
+
+// Glyph placed by text layout
+pub(crate) struct PlacedGlyph {
+    // Character index in the text, line breaks included
+    pub index: usize,
+    pub character: char,
+    pub source_rect: Rect,
+    // Top left corner relative to the text position, y goes up
+    pub position: Vec2,
+}
+
+// Font drawn from a glyph sheet, one pixel of the sheet is one framebuffer pixel. Characters the font
+// doesn't have are skipped.
+pub struct BitmapFont {
+    texture: Texture,
+    layout: FontLayout,
+}
+
+// Glyph metrics, everything text layout needs without the sheet texture
+struct FontLayout {
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+    line_height: f32,
+}
+
+impl BitmapFont {
+    // Sheet of equally sized cells, characters are assigned to cells row by row starting at the top left.
+    // Space advances by one cell when it's not in the sheet.
+    pub fn from_grid(png_bytes: &[u8], cell_width: u32, cell_height: u32, characters: &str) -> Result<BitmapFont, Error> {
+        let texture = Texture::from_png_bytes(png_bytes)?;
+        let layout = FontLayout::from_grid(texture.width(), texture.height(), cell_width, cell_height, characters)?;
+
+        Ok(BitmapFont { texture, layout })
+    }
+
+    // AngelCode BMFont text descriptor, fonts with a single page only. File names in the descriptor are
+    // not used, the page image is passed in.
+    pub fn from_bmfont(png_bytes: &[u8], descriptor: &str) -> Result<BitmapFont, Error> {
+        let mut glyphs = HashMap::new();
+        let mut kerning = HashMap::new();
+        let mut line_height = None;
+
+        for (line_idx, line) in descriptor.lines().enumerate() {
+            let line_number = line_idx + 1;
+            let (tag, values) = parse_bmfont_line(line);
+
+            match tag.as_str() {
+                "common" => {
+                    if parse_bmfont_value(&values, "pages", line_number)? != 1 {
+                        return Err(Error::asset_decode(Some(line_number), "Only fonts with a single page are supported"));
+                    }
+                    line_height = Some(parse_bmfont_value(&values, "lineHeight", line_number)? as f32);
+                }
+                "char" => {
+                    let character = parse_bmfont_character(&values, "id", line_number)?;
+                    if values.contains_key("page") && parse_bmfont_value(&values, "page", line_number)? != 0 {
+                        return Err(Error::asset_decode(Some(line_number), "Only fonts with a single page are supported"));
+                    }
+                    let glyph = Glyph {
+                        source_rect: Rect::new(
+                            parse_bmfont_value(&values, "x", line_number)? as f32,
+                            parse_bmfont_value(&values, "y", line_number)? as f32,
+                            parse_bmfont_value(&values, "width", line_number)? as f32,
+                            parse_bmfont_value(&values, "height", line_number)? as f32
+                        ),
+                        offset: Vec2::new(
+                            parse_bmfont_value(&values, "xoffset", line_number)? as f32,
+                            parse_bmfont_value(&values, "yoffset", line_number)? as f32
+                        ),
+                        advance: parse_bmfont_value(&values, "xadvance", line_number)? as f32,
+                    };
+                    glyphs.insert(character, glyph);
+                }
+                "kerning" => {
+                    let first = parse_bmfont_character(&values, "first", line_number)?;
+                    let second = parse_bmfont_character(&values, "second", line_number)?;
+                    kerning.insert((first, second), parse_bmfont_value(&values, "amount", line_number)? as f32);
+                }
+                _ => {}
+            }
+        }
+
+        let line_height = match line_height {
+            Some(line_height) => line_height,
+            None => return Err(Error::asset_decode(None, "Descriptor has no common line"))
+        };
+        let texture = Texture::from_png_bytes(png_bytes)?;
+
+        Ok(BitmapFont { texture, layout: FontLayout { glyphs, kerning, line_height } })
+    }
+
+    pub fn get_texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn get_line_height(&self) -> f32 {
+        self.layout.line_height
+    }
+
+    // Size of the text block in framebuffer pixels
+    pub fn measure_text(&self, text: &str, wrap_width: Option<f32>) -> Vec2 {
+        self.layout.measure_text(text, wrap_width)
+    }
+
+    pub(crate) fn layout_text(&self, text: &str, alignment: TextAlignment, wrap_width: Option<f32>) -> Vec<PlacedGlyph> {
+        self.layout.layout_text(text, alignment, wrap_width)
+    }
+}
+
+impl FontLayout {
+    // Cells of a sheet with the given size in pixels
+    fn from_grid(sheet_width: u32, sheet_height: u32, cell_width: u32, cell_height: u32, characters: &str) -> Result<FontLayout, Error> {
+        if cell_width == 0 || cell_height == 0 {
+            return Err(Error::asset_decode(None, "Glyph cell size can't be zero"));
+        }
+
+        let columns = sheet_width / cell_width;
+        let rows = sheet_height / cell_height;
+        if characters.chars().count() > (columns * rows) as usize {
+            return Err(Error::asset_decode(None, &format!(
+                "Glyph sheet has {} cells of {}x{}, but {} characters were given",
+                columns * rows,
+                cell_width,
+                cell_height,
+                characters.chars().count()
+            )));
+        }
+
+        let mut glyphs = HashMap::new();
+        for (cell, character) in characters.chars().enumerate() {
+            let cell = cell as u32;
+            let source_rect = Rect::new(
+                ((cell % columns) * cell_width) as f32,
+                ((cell / columns) * cell_height) as f32,
+                cell_width as f32,
+                cell_height as f32
+            );
+            glyphs.insert(character, Glyph { source_rect, offset: Vec2::ZERO, advance: cell_width as f32 });
+        }
+        glyphs.entry(' ').or_insert(Glyph { source_rect: Rect::new(0.0, 0.0, 0.0, 0.0), offset: Vec2::ZERO, advance: cell_width as f32 });
+
+        Ok(FontLayout { glyphs, kerning: HashMap::new(), line_height: cell_height as f32 })
+    }
+
+    fn measure_text(&self, text: &str, wrap_width: Option<f32>) -> Vec2 {
+        let lines = self.break_lines(text, wrap_width);
+        let width = lines.iter().map(|line| self.measure_line(line)).fold(0.0, f32::max);
+        Vec2::new(width, lines.len() as f32 * self.line_height)
+    }
+
+    fn layout_text(&self, text: &str, alignment: TextAlignment, wrap_width: Option<f32>) -> Vec<PlacedGlyph> {
+        let mut placed_glyphs = vec![];
+
+        for (line_idx, line) in self.break_lines(text, wrap_width).iter().enumerate() {
+            // Whole pixels, so that glyphs of centered lines don't end up between framebuffer pixels
+            let mut pen_x = match alignment {
+                TextAlignment::Left => 0.0,
+                TextAlignment::Center => -(self.measure_line(line) / 2.0).floor(),
+                TextAlignment::Right => -self.measure_line(line),
+            };
+            let line_top = -(line_idx as f32) * self.line_height;
+
+            let mut previous: Option<char> = None;
+            for (index, character) in line {
+                let glyph = match self.glyphs.get(character) {
+                    Some(glyph) => glyph,
+                    None => continue
+                };
+                pen_x += self.get_kerning(previous, *character);
+                previous = Some(*character);
+
+                if glyph.source_rect.width > 0.0 && glyph.source_rect.height > 0.0 {
+                    placed_glyphs.push(PlacedGlyph {
+                        index: *index,
+                        character: *character,
+                        source_rect: glyph.source_rect,
+                        position: Vec2::new(pen_x + glyph.offset.x, line_top - glyph.offset.y)
+                    });
+                }
+                pen_x += glyph.advance;
+            }
+        }
+
+        placed_glyphs
+    }
+
+    // Characters of every line with their index in the text
+    fn break_lines(&self, text: &str, wrap_width: Option<f32>) -> Vec<Vec<(usize, char)>> {
+        let mut lines = vec![];
+        let mut line: Vec<(usize, char)> = vec![];
+
+        for (index, character) in text.chars().enumerate() {
+            if character == '\n' {
+                lines.push(std::mem::take(&mut line));
+                continue;
+            }
+            line.push((index, character));
+
+            let wrap_width = match wrap_width {
+                Some(wrap_width) => wrap_width,
+                None => continue
+            };
+            if character == ' ' || line.len() < 2 || self.measure_line(&line) <= wrap_width {
+                continue;
+            }
+
+            // Break after the last space, or before this character when the word is wider than the line. Start
+            // of the word moved to the next line can still be too wide with kerning or wide glyphs, it's split
+            // again before this character.
+            loop {
+                let rest = match line.iter().rposition(|(_, character)| *character == ' ') {
+                    Some(space) => line.split_off(space + 1),
+                    None => line.split_off(line.len() - 1),
+                };
+                while line.last().is_some_and(|(_, character)| *character == ' ') {
+                    line.pop();
+                }
+                lines.push(std::mem::replace(&mut line, rest));
+
+                if line.len() < 2 || self.measure_line(&line) <= wrap_width {
+                    break;
+                }
+            }
+        }
+        lines.push(line);
+
+        lines
+    }
+
+    fn measure_line(&self, line: &[(usize, char)]) -> f32 {
+        let mut width = 0.0;
+        let mut previous: Option<char> = None;
+        for (_, character) in line {
+            if let Some(glyph) = self.glyphs.get(character) {
+                width += self.get_kerning(previous, *character) + glyph.advance;
+                previous = Some(*character);
+            }
+        }
+        width
+    }
+
+    fn get_kerning(&self, previous: Option<char>, character: char) -> f32 {
+        previous
+            .and_then(|previous| self.kerning.get(&(previous, character)))
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+// Tag and key=value pairs of a descriptor line, quoted values may contain spaces
+fn parse_bmfont_line(line: &str) -> (String, HashMap<String, String>) {
+    let mut tokens: Vec<String> = vec![];
+    let mut token = String::new();
+    let mut in_quotes = false;
+
+    for character in line.chars() {
+        match character {
+            '"' => in_quotes = !in_quotes,
+            character if character.is_whitespace() && !in_quotes => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            character => token.push(character),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    let tag = tokens.first().cloned().unwrap_or_default();
+    let values = tokens.iter()
+        .skip(1)
+        .filter_map(|token| token.split_once('='))
+        .map(|(key, value)| (String::from(key), String::from(value)))
+        .collect();
+
+    (tag, values)
+}
+
+fn parse_bmfont_value(values: &HashMap<String, String>, key: &str, line_number: usize) -> Result<i32, Error> {
+    let value = match values.get(key) {
+        Some(value) => value,
+        None => return Err(Error::asset_decode(Some(line_number), &format!("Missing {} value", key)))
+    };
+    value.parse::<i32>()
+        .map_err(|_| Error::asset_decode(Some(line_number), &format!("Invalid {} value: {}", key, value)))
+}
+
+fn parse_bmfont_character(values: &HashMap<String, String>, key: &str, line_number: usize) -> Result<char, Error> {
+    let id = parse_bmfont_value(values, key, line_number)?;
+    char::from_u32(id as u32)
+        .ok_or_else(|| Error::asset_decode(Some(line_number), &format!("Invalid character id: {}", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 8x8 cells, 'a' to 'h' in the top row and 'i' to 'p' in the second
+    fn grid_layout() -> FontLayout {
+        FontLayout::from_grid(64, 16, 8, 8, "abcdefghijklmnop").unwrap()
+    }
+
+    fn line_texts(layout: &FontLayout, text: &str, wrap_width: f32) -> Vec<String> {
+        layout.break_lines(text, Some(wrap_width)).iter()
+            .map(|line| line.iter().map(|(_, character)| *character).collect())
+            .collect()
+    }
+
+    #[test]
+    fn grid_glyphs_are_placed_row_by_row() {
+        let placed = grid_layout().layout_text("aj\nb", TextAlignment::Left, None);
+
+        let positions: Vec<Vec2> = placed.iter().map(|glyph| glyph.position).collect();
+        assert_eq!(positions, vec![Vec2::new(0.0, 0.0), Vec2::new(8.0, 0.0), Vec2::new(0.0, -8.0)]);
+        assert!(placed[1].source_rect == Rect::new(8.0, 8.0, 8.0, 8.0));
+        assert_eq!(placed[2].index, 3);
+    }
+
+    #[test]
+    fn lines_break_between_words() {
+        let layout = grid_layout();
+
+        assert_eq!(line_texts(&layout, "abc defg hi", 32.0), vec!["abc", "defg", "hi"]);
+        assert_eq!(layout.measure_text("abc defg hi", Some(32.0)), Vec2::new(32.0, 24.0));
+    }
+
+    #[test]
+    fn words_wider_than_the_line_are_split() {
+        assert_eq!(line_texts(&grid_layout(), "abcdefghij", 24.0), vec!["abc", "def", "ghi", "j"]);
+    }
+
+    #[test]
+    fn word_moved_to_next_line_is_split_again_when_too_wide() {
+        let mut layout = grid_layout();
+        layout.kerning.insert(('d', 'e'), 16.0);
+
+        let lines = line_texts(&layout, "a bcdef", 40.0);
+        assert_eq!(lines, vec!["a", "bcd", "ef"]);
+        for line in layout.break_lines("a bcdef", Some(40.0)) {
+            assert!(layout.measure_line(&line) <= 40.0);
+        }
+    }
+}
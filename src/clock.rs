@@ -0,0 +1,232 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+pub trait TimeSource {
+    // Time passed since an arbitrary starting point, must never go backwards
+    fn now(&self) -> Duration;
+}
+
+pub struct MonotonicTimeSource {
+    start: Instant,
+}
+
+impl MonotonicTimeSource {
+    pub fn new() -> MonotonicTimeSource {
+        MonotonicTimeSource::default()
+    }
+}
+
+impl Default for MonotonicTimeSource {
+    fn default() -> MonotonicTimeSource {
+        MonotonicTimeSource { start: Instant::now() }
+    }
+}
+
+impl TimeSource for MonotonicTimeSource {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+// Time only moves when told to. Clones share the same time, so tests can keep one
+// and hand the other to a Clock.
+#[derive(Clone)]
+pub struct ManualTimeSource {
+    time: Rc<Cell<Duration>>,
+}
+
+impl ManualTimeSource {
+    pub fn new() -> ManualTimeSource {
+        ManualTimeSource::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.time.set(self.time.get() + duration);
+    }
+}
+
+impl Default for ManualTimeSource {
+    fn default() -> ManualTimeSource {
+        ManualTimeSource { time: Rc::new(Cell::new(Duration::ZERO)) }
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&self) -> Duration {
+        self.time.get()
+    }
+}
+
+pub struct Clock {
+    time_source: Box<dyn TimeSource>,
+    last_tick: Duration,
+}
+
+impl Clock {
+    pub fn new() -> Clock {
+        Clock::default()
+    }
+
+    pub fn from_source(time_source: Box<dyn TimeSource>) -> Clock {
+        let last_tick = time_source.now();
+        Clock { time_source, last_tick }
+    }
+
+    // Seconds since the clock was created
+    pub fn now(&self) -> f64 {
+        self.time_source.now().as_secs_f64()
+    }
+
+    // Seconds since the last tick, without starting a new one
+    pub fn elapsed(&self) -> f64 {
+        self.time_source.now().saturating_sub(self.last_tick).as_secs_f64()
+    }
+
+    // Returns seconds since the previous tick and starts a new one
+    pub fn tick(&mut self) -> f64 {
+        let now = self.time_source.now();
+        let elapsed = now.saturating_sub(self.last_tick);
+        self.last_tick = now;
+        elapsed.as_secs_f64()
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::from_source(Box::new(MonotonicTimeSource::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::Rsfx;
+    use crate::engine_settings::EngineSettings;
+    use crate::game_status::GameStatus;
+    use crate::graphics_settings::GraphicsSettings;
+    use crate::input::Input;
+    use crate::internal::null_window::NullWindow;
+    use crate::internal::render_backend::RenderBackend;
+    use crate::renderer::Renderer;
+    use crate::scene::{Scene, SceneTransition};
+
+    // 20 ms, exact in both Duration and f64 seconds so every frame runs exactly one tick
+    const TICKS_PER_SECOND: u32 = 50;
+
+    // Records delta_time of every update, then changes the game time the way the test asks for that update
+    struct TimeScene<F: FnMut(usize, &mut GameStatus)> {
+        delta_times: Rc<RefCell<Vec<f64>>>,
+        change_time: F,
+    }
+
+    impl<F: FnMut(usize, &mut GameStatus)> Scene for TimeScene<F> {
+        fn on_start(&mut self, _renderer: &mut Renderer) { }
+
+        fn on_update(&mut self, game_status: &mut GameStatus, _renderer: &mut Renderer, _input: &Input, delta_time: f64) -> Option<SceneTransition> {
+            let update_idx = self.delta_times.borrow().len();
+            self.delta_times.borrow_mut().push(delta_time);
+            (self.change_time)(update_idx, game_status);
+            None
+        }
+
+        fn on_render(&mut self, _renderer: &mut Renderer, _alpha: f64) { }
+
+        fn on_destroy(&mut self) { }
+    }
+
+    // Runs the main loop on a clock whose time source moves one tick per frame
+    fn run_updates<F>(ticks: u64, change_time: F) -> Vec<f64>
+        where F: FnMut(usize, &mut GameStatus) + 'static
+    {
+        let engine_settings = EngineSettings { ticks_per_second: TICKS_PER_SECOND, ..EngineSettings::new() };
+        let tick_duration = Duration::from_secs(1) / TICKS_PER_SECOND;
+
+        let time_source = ManualTimeSource::new();
+        let mut clock = Clock::from_source(Box::new(time_source.clone()));
+        let mut frame_timer = || {
+            time_source.advance(tick_duration);
+            clock.tick()
+        };
+
+        let delta_times = Rc::new(RefCell::new(vec![]));
+        let scene = TimeScene { delta_times: delta_times.clone(), change_time };
+
+        let _headless_scope = RenderBackend::enter_headless();
+        let mut window = NullWindow::new(320, 240, |_, _| {});
+        let mut renderer = Renderer::new(RenderBackend::Recording(vec![]));
+        Rsfx::main_loop(&mut window, &mut renderer, GraphicsSettings::new(), &engine_settings, Box::new(scene), &mut frame_timer, Some(ticks)).unwrap();
+
+        let delta_times = delta_times.borrow().clone();
+        delta_times
+    }
+
+    #[test]
+    fn tick_returns_time_passed_on_the_source() {
+        let time_source = ManualTimeSource::new();
+        let mut clock = Clock::from_source(Box::new(time_source.clone()));
+
+        time_source.advance(Duration::from_millis(250));
+        assert_eq!(clock.elapsed(), 0.25);
+        assert_eq!(clock.tick(), 0.25);
+        assert_eq!(clock.tick(), 0.0);
+
+        time_source.advance(Duration::from_micros(500));
+        assert_eq!(clock.tick(), 0.0005);
+        assert_eq!(clock.now(), 0.2505);
+    }
+
+    #[test]
+    fn delta_time_follows_the_clock() {
+        let tick = 1.0 / TICKS_PER_SECOND as f64;
+        assert_eq!(run_updates(3, |_, _| {}), vec![tick, tick, tick]);
+    }
+
+    #[test]
+    fn time_scale_changes_delta_time_from_the_next_update() {
+        let tick = 1.0 / TICKS_PER_SECOND as f64;
+        let delta_times = run_updates(4, |update_idx, game_status| match update_idx {
+            0 => game_status.set_time_scale(0.5),
+            2 => game_status.set_time_scale(2.0),
+            _ => {}
+        });
+
+        assert_eq!(delta_times, vec![tick, tick * 0.5, tick * 0.5, tick * 2.0]);
+    }
+
+    #[test]
+    fn paused_game_gets_zero_delta_time_until_resumed() {
+        let tick = 1.0 / TICKS_PER_SECOND as f64;
+        let delta_times = run_updates(5, |update_idx, game_status| match update_idx {
+            0 => game_status.set_time_scale(0.5),
+            1 => game_status.pause(),
+            3 => game_status.resume(),
+            _ => {}
+        });
+
+        // Time scale set before the pause applies again after it
+        assert_eq!(delta_times, vec![tick, tick * 0.5, 0.0, 0.0, tick * 0.5]);
+    }
+}
@@ -24,7 +24,7 @@ use std::hash::{DefaultHasher, Hash, Hasher};
 use glam::Vec3;
 use num_traits::Zero;
 
-use crate::{matrices, mesh::MeshData};
+use crate::{error::Error, matrices, mesh::MeshData};
 
 use super::{aabb::AABB, colliders::{face::Face, Collidable}, CollisionResult};
 
@@ -50,27 +50,26 @@ impl StaticWorld {
         StaticWorld { node_size: node_size as i32, top_level_nodes: HashMap::new() }
     }
 
-    pub fn add_mesh(&mut self, mesh_data: &MeshData, position: &Vec3, rotation: &Vec3, scale: f32) -> Result<(), &'static str> {
-        let vertices = mesh_data.get_vertices();
-        let vertices_count = mesh_data.get_vertices_count() as usize;
+    pub fn add_mesh(&mut self, mesh_data: &MeshData, position: &Vec3, rotation: &Vec3, scale: f32) -> Result<(), Error> {
+        let vertices_count = mesh_data.get_elements_count();
 
         if vertices_count % 3 != 0 {
-            return Err("Mesh vertices count is not divisable by 3!")
+            return Err(Error::CollisionSetup(String::from("Mesh vertices count is not divisable by 3!")))
         }
 
         let transform = matrices::build_transformation_matrix(position, rotation, scale);
 
         for idx in (0..vertices_count).step_by(3) {
-            let vertex_a = transform.transform_point3(vertices[idx + 0].get_position().clone());
-            let vertex_b = transform.transform_point3(vertices[idx + 1].get_position().clone());
-            let vertex_c = transform.transform_point3(vertices[idx + 2].get_position().clone());
+            let vertex_a = transform.transform_point3(mesh_data.get_element(idx + 0).get_position().clone());
+            let vertex_b = transform.transform_point3(mesh_data.get_element(idx + 1).get_position().clone());
+            let vertex_c = transform.transform_point3(mesh_data.get_element(idx + 2).get_position().clone());
 
             let face = Face::new(vertex_a, vertex_b, vertex_c);
 
             let face_size = face.aabb.max - face.aabb.min;
             let max_face_dimension = face_size.x.max(face_size.y.max(face_size.z));
             if max_face_dimension > self.node_size as f32 {
-                return Err("Mesh face dimensions are bigger than node's dimensions")
+                return Err(Error::CollisionSetup(String::from("Mesh face dimensions are bigger than node's dimensions")))
             }
 
             self.add_face(face);
@@ -0,0 +1,51 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+pub struct EngineSettings {
+    // How many times per second Scene::on_update is called
+    pub ticks_per_second: u32,
+    // Upper bound of catch-up ticks per frame. Time beyond that is dropped so a long stall
+    // doesn't turn into an ever growing backlog of updates (spiral of death)
+    pub max_ticks_per_frame: u32,
+    // Frame rate limit used when vsync is off, None renders as fast as possible
+    pub frame_rate_cap: Option<u32>,
+    // Color the screen is cleared with until a scene sets its own, same 0-255 range as Renderer::set_clear_color
+    pub clear_color: (f32, f32, f32),
+}
+
+impl EngineSettings {
+    pub fn new() -> EngineSettings {
+        EngineSettings::default()
+    }
+
+    pub(crate) fn get_tick_duration(&self) -> f64 {
+        1.0 / self.ticks_per_second.max(1) as f64
+    }
+
+    pub(crate) fn get_max_ticks_per_frame(&self) -> u32 {
+        self.max_ticks_per_frame.max(1)
+    }
+}
+
+impl Default for EngineSettings {
+    fn default() -> EngineSettings {
+        EngineSettings { ticks_per_second: 60, max_ticks_per_frame: 5, frame_rate_cap: None, clear_color: (0.0, 0.0, 0.0) }
+    }
+}
@@ -0,0 +1,91 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ShaderStage {
+    Vertex,
+    Geometry,
+    Fragment,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    // SDL, window or GL context could not be created
+    WindowInit(String),
+    // GL objects the engine depends on (framebuffers, render passes) could not be created
+    GlInit(String),
+    ShaderCompile { stage: ShaderStage, info_log: String },
+    ShaderLink { info_log: String },
+    // File name is only known when the caller provides it, see Error::with_file
+    AssetDecode { file: Option<String>, line: Option<usize>, message: String },
+    CollisionSetup(String),
+    // Image could not be encoded or written out
+    AssetEncode(String),
+    // Operation needs something the current backend doesn't have, like a GL context in headless runs
+    Unsupported(String),
+    // Fixed size engine resource, like the light list, is full
+    LimitReached(String),
+    // Value passed in by the caller can't be used, like a zero sized render target
+    InvalidArgument(String),
+}
+
+impl Error {
+    pub(crate) fn asset_decode(line: Option<usize>, message: &str) -> Error {
+        Error::AssetDecode { file: None, line, message: String::from(message) }
+    }
+
+    // Asset loaders work with already loaded data, this attaches the name of the file it came from
+    pub fn with_file(self, file_name: &str) -> Error {
+        match self {
+            Error::AssetDecode { line, message, .. } => Error::AssetDecode { file: Some(String::from(file_name)), line, message },
+            error => error
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::WindowInit(message) => write!(f, "Failed to initialize window: {}", message),
+            Error::GlInit(message) => write!(f, "Failed to initialize OpenGL: {}", message),
+            Error::ShaderCompile { stage, info_log } => write!(f, "Failed to compile {:?} shader: {}", stage, info_log),
+            Error::ShaderLink { info_log } => write!(f, "Failed to link shader program: {}", info_log),
+            Error::AssetDecode { file, line, message } => {
+                write!(f, "Failed to decode asset")?;
+                if let Some(file) = file {
+                    write!(f, " {}", file)?;
+                }
+                if let Some(line) = line {
+                    write!(f, " at line {}", line)?;
+                }
+                write!(f, ": {}", message)
+            }
+            Error::CollisionSetup(message) => write!(f, "Failed to set up collision: {}", message),
+            Error::AssetEncode(message) => write!(f, "Failed to encode asset: {}", message),
+            Error::Unsupported(message) => write!(f, "Unsupported operation: {}", message),
+            Error::LimitReached(message) => write!(f, "Limit reached: {}", message),
+            Error::InvalidArgument(message) => write!(f, "Invalid argument: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error { }
@@ -17,13 +17,29 @@
 // along with RSFX. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use crate::error::Error;
+use crate::graphics_settings::GraphicsSettings;
+
 pub struct GameStatus {
     should_quit: bool,
+    time_scale: f64,
+    paused: bool,
+    graphics_settings: GraphicsSettings,
+    requested_graphics_settings: Option<GraphicsSettings>,
+    // Why the last requested settings were rejected
+    graphics_settings_error: Option<Error>,
 }
 
 impl GameStatus {
     pub fn new() -> GameStatus {
-        GameStatus { should_quit: false }
+        GameStatus {
+            should_quit: false,
+            time_scale: 1.0,
+            paused: false,
+            graphics_settings: GraphicsSettings::new(),
+            requested_graphics_settings: None,
+            graphics_settings_error: None,
+        }
     }
 
     pub fn quit(&mut self) {
@@ -33,4 +49,63 @@ impl GameStatus {
     pub fn should_quit(&self) -> bool {
         self.should_quit
     }
+
+    // Multiplier for delta_time handed to Scene::on_update, below 1.0 for slow motion
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn get_time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    // Scenes keep getting updated while paused, just with zero delta_time, so they can still react to input
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Settings the game is currently running with
+    pub fn get_graphics_settings(&self) -> &GraphicsSettings {
+        &self.graphics_settings
+    }
+
+    // Settings are applied after the current update. If they can't be applied (for instance Native resolution
+    // on a display that is not 16:9) the game keeps running with the previous ones.
+    pub fn set_graphics_settings(&mut self, graphics_settings: GraphicsSettings) {
+        self.requested_graphics_settings = Some(graphics_settings);
+    }
+
+    pub(crate) fn take_requested_graphics_settings(&mut self) -> Option<GraphicsSettings> {
+        self.requested_graphics_settings.take()
+    }
+
+    // Set when the last settings passed to set_graphics_settings could not be applied, cleared once a later
+    // request succeeds
+    pub fn get_graphics_settings_error(&self) -> Option<&Error> {
+        self.graphics_settings_error.as_ref()
+    }
+
+    pub(crate) fn set_applied_graphics_settings(&mut self, graphics_settings: GraphicsSettings) {
+        self.graphics_settings = graphics_settings;
+        self.graphics_settings_error = None;
+    }
+
+    pub(crate) fn set_graphics_settings_error(&mut self, error: Error) {
+        self.graphics_settings_error = Some(error);
+    }
+
+    pub(crate) fn scale_delta_time(&self, delta_time: f64) -> f64 {
+        if self.paused {
+            return 0.0;
+        }
+        delta_time * self.time_scale
+    }
 }
\ No newline at end of file
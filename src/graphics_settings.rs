@@ -1,5 +1,6 @@
 
 // Determines
+#[derive(Copy, Clone, Eq, PartialEq)]
 pub enum RenderResolution {
     W427h240,
     W640h360,
@@ -7,14 +8,69 @@ pub enum RenderResolution {
     Native
 }
 
+// How coarse the grid is that vertices get snapped to on screen, the source of PSX polygon wobble
+#[derive(Copy, Clone, PartialEq)]
+pub enum VertexSnapping {
+    Off,
+    // One grid cell per framebuffer pixel
+    Native,
+    // Fixed grid, independent of render resolution
+    Custom { width: u32, height: u32 },
+    // Native grid near the camera, getting coarser with depth. At halving_distance the grid is half as
+    // fine, at twice that a third and so on. Zero or less behaves like Native.
+    DepthDependent { halving_distance: f32 },
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Dithering {
+    Off,
+    Bayer2x2,
+    // Pattern size PSX GPU used
+    Bayer4x4,
+    Bayer8x8,
+}
+
+// Color depth of the final image, reduced in a post-process pass after the scene is drawn
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum ColorDepth {
+    Full,
+    // 5 bits per channel like PSX framebuffer, ordered dithering hides the banding
+    Rgb555 { dithering: Dithering },
+}
+
+#[derive(Copy, Clone, PartialEq)]
 pub struct GraphicsSettings {
     pub vsync: bool,
     pub fullscreen: bool,
     pub render_resolution: RenderResolution,
+    pub vertex_snapping: VertexSnapping,
+    pub color_depth: ColorDepth,
+}
+
+impl RenderResolution {
+    pub(crate) fn get_size(&self, display_width: i32, display_height: i32) -> (i32, i32) {
+        match self {
+            RenderResolution::W427h240 => (427, 240),
+            RenderResolution::W640h360 => (640, 360),
+            RenderResolution::W854h480 => (854, 480),
+            RenderResolution::Native => (display_width, display_height)
+        }
+    }
+}
+
+impl Dithering {
+    pub(crate) fn get_matrix_size(&self) -> i32 {
+        match self {
+            Dithering::Off => 0,
+            Dithering::Bayer2x2 => 2,
+            Dithering::Bayer4x4 => 4,
+            Dithering::Bayer8x8 => 8,
+        }
+    }
 }
 
 impl GraphicsSettings {
     pub fn new() -> GraphicsSettings {
-        GraphicsSettings { vsync: false, fullscreen: true, render_resolution: RenderResolution::W854h480 }
+        GraphicsSettings { vsync: false, fullscreen: true, render_resolution: RenderResolution::W854h480, vertex_snapping: VertexSnapping::Native, color_depth: ColorDepth::Full }
     }
 }
\ No newline at end of file
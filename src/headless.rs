@@ -0,0 +1,283 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use glam::Mat4;
+
+use crate::game_status::GameStatus;
+
+// Exposed so headless tests can assert on what a scene asked the renderer to do
+pub use crate::internal::renderable::Renderable;
+pub use crate::internal::renderer_command::RendererCommand;
+
+// Everything the scenes submitted for one rendered frame
+pub struct RecordedFrame {
+    commands: Vec<RendererCommand>,
+    instance_transforms: Vec<Mat4>,
+}
+
+pub struct HeadlessReport {
+    game_status: GameStatus,
+    ticks: u64,
+    frames: Vec<RecordedFrame>,
+}
+
+impl RecordedFrame {
+    pub(crate) fn new(commands: Vec<RendererCommand>, instance_transforms: Vec<Mat4>) -> RecordedFrame {
+        RecordedFrame { commands, instance_transforms }
+    }
+
+    pub fn get_commands(&self) -> &Vec<RendererCommand> {
+        &self.commands
+    }
+
+    // Transforms of every instanced draw of the frame, RenderInstanced refers to them by index and count
+    pub fn get_instance_transforms(&self) -> &Vec<Mat4> {
+        &self.instance_transforms
+    }
+}
+
+impl HeadlessReport {
+    pub(crate) fn new(game_status: GameStatus, ticks: u64, frames: Vec<RecordedFrame>) -> HeadlessReport {
+        HeadlessReport { game_status, ticks, frames }
+    }
+
+    pub fn get_game_status(&self) -> &GameStatus {
+        &self.game_status
+    }
+
+    // Number of scene updates that were run, less than requested if the game quit early
+    pub fn get_ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    // One per rendered frame
+    pub fn get_frames(&self) -> &Vec<RecordedFrame> {
+        &self.frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use glam::{Mat4, Vec2, Vec3};
+
+    use super::*;
+    use crate::Rsfx;
+    use crate::engine_settings::EngineSettings;
+    use crate::graphics_settings::GraphicsSettings;
+    use crate::input::{Input, Key, State};
+    use crate::internal::render_backend::RenderBackend;
+    use crate::material::Material;
+    use crate::mesh::{Mesh, MeshData, MeshIndices};
+    use crate::renderer::Renderer;
+    use crate::scene::{Scene, SceneTransition};
+    use crate::texture::{ImageMode, Texture};
+    use crate::vertex_data::VertexData;
+
+    const TICKS: u64 = 5;
+
+    // Builds its resources on start and draws the mesh once on its own and twice instanced every frame
+    struct ResourceScene {
+        resources: Option<(Mesh, Rc<Texture>, Material)>,
+        instance_transforms: Vec<Mat4>,
+    }
+
+    impl Scene for ResourceScene {
+        fn on_start(&mut self, _renderer: &mut Renderer) {
+            let vertex_data = vec![
+                VertexData::new(Vec3::new(0.0, 0.0, 0.0), Vec2::new(0.0, 0.0), Vec3::Z),
+                VertexData::new(Vec3::new(1.0, 0.0, 0.0), Vec2::new(1.0, 0.0), Vec3::Z),
+                VertexData::new(Vec3::new(0.0, 1.0, 0.0), Vec2::new(0.0, 1.0), Vec3::Z),
+            ];
+            let mesh = Mesh::from_mesh_data(&MeshData::from_indexed_data(vertex_data, MeshIndices::compact(vec![0, 1, 2])));
+            let texture = Rc::new(Texture::from_data(&vec![255; 2 * 2 * 4], 2, 2, ImageMode::RGBA));
+            let material = Material::new(&texture);
+            self.resources = Some((mesh, texture, material));
+        }
+
+        fn on_update(&mut self, _game_status: &mut GameStatus, _renderer: &mut Renderer, _input: &Input, _delta_time: f64) -> Option<SceneTransition> {
+            None
+        }
+
+        fn on_render(&mut self, renderer: &mut Renderer, _alpha: f64) {
+            let (mesh, _, material) = self.resources.as_ref().unwrap();
+            renderer.clear_screen();
+            renderer.set_transformation_matrix(&Mat4::IDENTITY);
+            renderer.render(mesh, material);
+            renderer.render_instanced(mesh, material, &self.instance_transforms);
+        }
+
+        fn on_destroy(&mut self) {
+            self.resources = None;
+        }
+    }
+
+    // Logs every hook call, quits through GameStatus in slow motion once space is pressed
+    struct ScriptedScene {
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Scene for ScriptedScene {
+        fn on_start(&mut self, _renderer: &mut Renderer) {
+            self.log.borrow_mut().push(String::from("start"));
+        }
+
+        fn on_update(&mut self, game_status: &mut GameStatus, _renderer: &mut Renderer, input: &Input, _delta_time: f64) -> Option<SceneTransition> {
+            let space_pressed = input.get_key_state(Key::Space) == State::Down;
+            self.log.borrow_mut().push(format!("update {}", space_pressed));
+            if space_pressed {
+                game_status.set_time_scale(0.5);
+                game_status.quit();
+            }
+            None
+        }
+
+        fn on_render(&mut self, renderer: &mut Renderer, _alpha: f64) {
+            renderer.clear_screen();
+            self.log.borrow_mut().push(String::from("render"));
+        }
+
+        fn on_destroy(&mut self) {
+            self.log.borrow_mut().push(String::from("destroy"));
+        }
+    }
+
+    fn run_scripted<F>(ticks: u64, input_script: F) -> (HeadlessReport, Vec<String>)
+        where F: FnMut(u64, &mut Input)
+    {
+        let log = Rc::new(RefCell::new(vec![]));
+        let scene = ScriptedScene { log: log.clone() };
+        let report = Rsfx::new()
+            .run_headless(GraphicsSettings::new(), EngineSettings::new(), Box::new(scene), ticks, input_script)
+            .unwrap();
+        let log = log.borrow().clone();
+        (report, log)
+    }
+
+    // Commands that are not uniforms, by name
+    fn command_names(frame: &RecordedFrame) -> Vec<&'static str> {
+        frame.get_commands().iter()
+            .filter_map(|command| match command {
+                RendererCommand::SetClearColor(_, _, _) => Some("SetClearColor"),
+                RendererCommand::ClearScreen() => Some("ClearScreen"),
+                RendererCommand::Render(_) => Some("Render"),
+                RendererCommand::RenderInstanced(_, _, _) => Some("RenderInstanced"),
+                _ => None
+            })
+            .collect()
+    }
+
+    #[test]
+    fn headless_scene_creates_meshes_and_textures() {
+        let instance_transforms = vec![Mat4::from_translation(Vec3::X), Mat4::from_translation(Vec3::Y)];
+        let scene = ResourceScene { resources: None, instance_transforms: instance_transforms.clone() };
+
+        let report = Rsfx::new()
+            .run_headless(GraphicsSettings::new(), EngineSettings::new(), Box::new(scene), TICKS, |_, _| {})
+            .unwrap();
+
+        assert_eq!(report.get_ticks(), TICKS);
+        assert_eq!(report.get_frames().len(), TICKS as usize);
+        assert!(!RenderBackend::is_headless());
+
+        for frame in report.get_frames() {
+            let renderables: Vec<&Renderable> = frame.get_commands().iter()
+                .filter_map(|command| command.get_renderable())
+                .collect();
+            assert_eq!(renderables.len(), 2);
+            assert_eq!(renderables[0].vertices_count, 3);
+            assert_ne!(renderables[0].mesh_id, 0);
+            assert_ne!(renderables[0].texture_ids[0], 0);
+            assert_eq!(renderables[1].mesh_id, renderables[0].mesh_id);
+
+            let (first_instance, instance_count) = frame.get_commands().iter()
+                .find_map(|command| match command {
+                    RendererCommand::RenderInstanced(_, first_instance, instance_count) => Some((*first_instance as usize, *instance_count as usize)),
+                    _ => None
+                })
+                .unwrap();
+            assert_eq!(&frame.get_instance_transforms()[first_instance..first_instance + instance_count], instance_transforms.as_slice());
+        }
+    }
+
+    #[test]
+    fn every_tick_is_updated_and_rendered_between_start_and_destroy() {
+        let (report, log) = run_scripted(2, |_, _| {});
+
+        assert_eq!(report.get_ticks(), 2);
+        assert_eq!(report.get_frames().len(), 2);
+        assert_eq!(log, vec!["start", "update false", "render", "update false", "render", "destroy"]);
+    }
+
+    #[test]
+    fn scripted_input_reaches_on_update() {
+        let (report, log) = run_scripted(10, |frame, input| {
+            if frame == 2 {
+                input.set_key_state(Key::Space, State::Down);
+            }
+        });
+
+        // Scene quits during the third tick, before that frame is rendered
+        assert_eq!(report.get_ticks(), 3);
+        assert_eq!(report.get_frames().len(), 2);
+        assert_eq!(log, vec!["start", "update false", "render", "update false", "render", "update true", "destroy"]);
+
+        let game_status = report.get_game_status();
+        assert!(game_status.should_quit());
+        assert_eq!(game_status.get_time_scale(), 0.5);
+    }
+
+    #[test]
+    fn request_quit_ends_run_and_destroys_scenes() {
+        let (report, log) = run_scripted(10, |frame, input| {
+            if frame == 3 {
+                input.request_quit();
+            }
+        });
+
+        assert_eq!(report.get_ticks(), 3);
+        assert_eq!(report.get_frames().len(), 3);
+        assert_eq!(log.last().unwrap(), "destroy");
+        assert_eq!(log.iter().filter(|entry| *entry == "update false").count(), 3);
+        // Quit came from the window, not from the game
+        assert!(!report.get_game_status().should_quit());
+    }
+
+    #[test]
+    fn recorded_commands_keep_submission_order() {
+        let scene = ResourceScene { resources: None, instance_transforms: vec![Mat4::IDENTITY] };
+
+        let report = Rsfx::new()
+            .run_headless(GraphicsSettings::new(), EngineSettings::new(), Box::new(scene), 2, |_, _| {})
+            .unwrap();
+
+        let frames = report.get_frames();
+        // Clear color set by the engine before the first frame ends up in it
+        assert_eq!(command_names(&frames[0]), vec!["SetClearColor", "ClearScreen", "Render", "RenderInstanced"]);
+        assert_eq!(command_names(&frames[1]), vec!["ClearScreen", "Render", "RenderInstanced"]);
+
+        let commands = frames[1].get_commands();
+        let transformation_idx = commands.iter().position(|command| matches!(command, RendererCommand::SetUniformMat4(_, _))).unwrap();
+        let render_idx = commands.iter().position(|command| matches!(command, RendererCommand::Render(_))).unwrap();
+        assert!(transformation_idx < render_idx);
+    }
+}
@@ -363,25 +363,7 @@ impl Input {
                 xrel,
                 yrel,
             } => {
-                let adjusted_x_rel = *xrel as f32 * self.cursor_x_sensitivity;
-                // Invert y value
-                let adjusted_y_rel = -(*yrel as f32) * self.cursor_y_sensitivity;
-                
-                self.cursor_x += adjusted_x_rel;
-                if self.cursor_x > self.max_cursor_x {
-                    self.cursor_x = self.max_cursor_x;
-                } else if self.cursor_x < self.min_cursor_x {
-                    self.cursor_x = self.min_cursor_x ;
-                }
-                self.cursor_y += adjusted_y_rel;
-                if self.cursor_y > self.max_cursor_y {
-                    self.cursor_y = self.max_cursor_y;
-                } else if self.cursor_y < self.min_cursor_y {
-                    self.cursor_y = self.min_cursor_y;
-                }
-                
-                self.cursor_delta_x = *xrel as f32;
-                self.cursor_delta_y = -(*yrel as f32);
+                self.move_cursor(*xrel, *yrel);
             }
             Event::MouseButtonDown {
                 timestamp,
@@ -417,11 +399,71 @@ impl Input {
         Ok(())
     }
     
+    pub(crate) fn set_screen_size(&mut self, screen_buffer_width: i32, screen_buffer_height: i32, screen_width: i32, screen_height: i32) {
+        self.screen_width = screen_width;
+        self.screen_height = screen_height;
+        self.max_cursor_x = (screen_buffer_width / 2) as f32;
+        self.max_cursor_y = (screen_buffer_height / 2) as f32;
+        self.min_cursor_x = (-screen_buffer_width / 2) as f32;
+        self.min_cursor_y = (-screen_buffer_height / 2) as f32;
+        self.cursor_x_sensitivity = screen_buffer_width as f32 / screen_width as f32;
+        self.cursor_y_sensitivity = screen_buffer_height as f32 / screen_height as f32;
+
+        // Keep the cursor inside the new bounds
+        self.cursor_x = self.cursor_x.clamp(self.min_cursor_x, self.max_cursor_x);
+        self.cursor_y = self.cursor_y.clamp(self.min_cursor_y, self.max_cursor_y);
+    }
+
     pub(crate) fn clear_states(&mut self) {
         self.cursor_delta_x = 0.0;
         self.cursor_delta_y = 0.0;
     }
 
+    // Copy with nothing pressed and no cursor movement, given to scenes that are updated in the background
+    pub(crate) fn to_idle(&self) -> Input {
+        let mut input = self.clone();
+        input.key_state.clear();
+        input.mouse_button_state.clear();
+        input.clear_states();
+        input
+    }
+
+    // Setters below are meant for scripted input in headless runs, window backed runs get their state from SDL events
+    pub fn set_key_state(&mut self, key: Key, state: State) {
+        self.key_state.insert(key, state);
+    }
+
+    pub fn set_button_state(&mut self, button: Button, state: State) {
+        self.mouse_button_state.insert(button, state);
+    }
+
+    // Relative motion in window pixels with y pointing down, same as reported by the mouse
+    pub fn move_cursor(&mut self, x_rel: i32, y_rel: i32) {
+        let adjusted_x_rel = x_rel as f32 * self.cursor_x_sensitivity;
+        // Invert y value
+        let adjusted_y_rel = -(y_rel as f32) * self.cursor_y_sensitivity;
+
+        self.cursor_x += adjusted_x_rel;
+        if self.cursor_x > self.max_cursor_x {
+            self.cursor_x = self.max_cursor_x;
+        } else if self.cursor_x < self.min_cursor_x {
+            self.cursor_x = self.min_cursor_x ;
+        }
+        self.cursor_y += adjusted_y_rel;
+        if self.cursor_y > self.max_cursor_y {
+            self.cursor_y = self.max_cursor_y;
+        } else if self.cursor_y < self.min_cursor_y {
+            self.cursor_y = self.min_cursor_y;
+        }
+
+        self.cursor_delta_x = x_rel as f32;
+        self.cursor_delta_y = -(y_rel as f32);
+    }
+
+    pub fn request_quit(&mut self) {
+        self.should_quit = true;
+    }
+
     pub fn get_key_state(&self, key: Key) -> State {
         match self.key_state.get(&key) {
             None => State::Up,
@@ -0,0 +1,157 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+use std::ffi::CString;
+
+use gl::types::{GLint, GLuint};
+use glam::{Mat3, Mat4, Vec2, Vec3, Vec4};
+
+use crate::background::{BackgroundLayer, Skybox};
+use crate::error::Error;
+use crate::internal::gl_renderer::GlRenderer;
+use crate::internal::shader_program::ShaderProgram;
+use crate::mesh::Mesh;
+use crate::renderer::BlendMode;
+use crate::vertex_data::VertexData;
+
+const UNIFORM_SKYBOX_VIEW_ROTATION_MATRIX_LOCATION: i32 = 0;
+const UNIFORM_SKYBOX_PROJECTION_MATRIX_LOCATION: i32 = 4;
+const UNIFORM_SKYBOX_PANORAMA_LOCATION: i32 = 8;
+const UNIFORM_LAYER_FRAMEBUFFER_SIZE_LOCATION: i32 = 0;
+const UNIFORM_LAYER_TEXTURE_SIZE_LOCATION: i32 = 1;
+const UNIFORM_LAYER_OFFSET_LOCATION: i32 = 2;
+const UNIFORM_LAYER_TINT_LOCATION: i32 = 3;
+
+// Skybox textures for commands, Renderer keeps the skybox alive while commands refer to it
+#[derive(Copy, Clone, PartialEq)]
+pub struct SkyboxDraw {
+    pub texture_ids: [GLuint; 6],
+    pub panorama: bool,
+}
+
+impl SkyboxDraw {
+    pub fn new(skybox: &Skybox) -> SkyboxDraw {
+        SkyboxDraw { texture_ids: skybox.get_texture_ids(), panorama: skybox.is_panorama() }
+    }
+}
+
+// Background layer as it was when drawn, commands can't hold its texture. Renderer keeps the texture
+// alive until the frame's passes ran.
+#[derive(Copy, Clone, PartialEq)]
+pub struct BackgroundLayerDraw {
+    pub texture_id: GLuint,
+    pub texture_size: Vec2,
+    pub offset: Vec2,
+    pub tint: Vec4,
+}
+
+impl BackgroundLayerDraw {
+    pub fn new(layer: &BackgroundLayer) -> BackgroundLayerDraw {
+        BackgroundLayerDraw {
+            texture_id: layer.get_texture().texture_id(),
+            texture_size: *layer.get_texture_size(),
+            offset: layer.offset,
+            tint: layer.tint
+        }
+    }
+}
+
+// Draws the skybox and background layers into the bound framebuffer, without fog, lighting or snapping
+pub struct BackgroundRenderer {
+    // Faces in the same order as skybox textures, 6 vertices each
+    skybox_mesh: Mesh,
+    skybox_shader: ShaderProgram,
+    layer_shader: ShaderProgram,
+}
+
+impl BackgroundRenderer {
+    pub fn new() -> Result<BackgroundRenderer, Error> {
+        let skybox_shader = ShaderProgram::load_shaders(
+            &CString::new(include_str!("shaders/skybox_shader.vert")).unwrap(),
+            &CString::new(include_str!("shaders/skybox_shader.frag")).unwrap(),
+        )?;
+        let layer_shader = ShaderProgram::load_shaders(
+            &CString::new(include_str!("shaders/screen_shader.vert")).unwrap(),
+            &CString::new(include_str!("shaders/background_layer_shader.frag")).unwrap(),
+        )?;
+
+        Ok(BackgroundRenderer { skybox_mesh: BackgroundRenderer::build_skybox_mesh(), skybox_shader, layer_shader })
+    }
+
+    fn build_skybox_mesh() -> Mesh {
+        // Center, right and up of every face as seen from inside the cube
+        let faces = [
+            (Vec3::X, Vec3::Z, Vec3::Y),
+            (Vec3::NEG_X, Vec3::NEG_Z, Vec3::Y),
+            (Vec3::Y, Vec3::X, Vec3::Z),
+            (Vec3::NEG_Y, Vec3::X, Vec3::NEG_Z),
+            (Vec3::Z, Vec3::NEG_X, Vec3::Y),
+            (Vec3::NEG_Z, Vec3::X, Vec3::Y),
+        ];
+
+        let mut vertex_data: Vec<VertexData> = Vec::with_capacity(faces.len() * 6);
+        for (center, right, up) in faces {
+            let corners = [
+                (center - right - up, Vec2::new(0.0, 0.0)),
+                (center + right - up, Vec2::new(1.0, 0.0)),
+                (center + right + up, Vec2::new(1.0, 1.0)),
+                (center - right + up, Vec2::new(0.0, 1.0)),
+            ];
+            for index in [0, 1, 2, 0, 2, 3] {
+                let (position, texture_coords) = corners[index];
+                vertex_data.push(VertexData::new(position, texture_coords, -center));
+            }
+        }
+
+        Mesh::from_raw_data(&vertex_data)
+    }
+
+    // Depth test is left off and depth writes are turned off through the blend mode, the caller restores them
+    pub fn draw(&self, gl_renderer: &GlRenderer, skybox: Option<&SkyboxDraw>, layers: &[&BackgroundLayerDraw], view_matrix: &Mat4, projection_matrix: &Mat4, framebuffer_size: Vec2) {
+        gl_renderer.disable_depth_test();
+        gl_renderer.set_blend_mode(BlendMode::Alpha);
+
+        if let Some(skybox) = skybox {
+            let view_rotation_matrix = Mat4::from_mat3(Mat3::from_mat4(*view_matrix));
+
+            self.skybox_shader.enable();
+            self.skybox_shader.set_uniform_mat4(UNIFORM_SKYBOX_VIEW_ROTATION_MATRIX_LOCATION, &view_rotation_matrix);
+            self.skybox_shader.set_uniform_mat4(UNIFORM_SKYBOX_PROJECTION_MATRIX_LOCATION, projection_matrix);
+            self.skybox_shader.set_uniform_int(UNIFORM_SKYBOX_PANORAMA_LOCATION, skybox.panorama as i32);
+
+            gl_renderer.bind_mesh(self.skybox_mesh.vao_id());
+            for (face, texture_id) in skybox.texture_ids.iter().enumerate() {
+                gl_renderer.bind_texture(*texture_id, gl::TEXTURE0);
+                gl_renderer.draw_arrays_range(face as GLint * 6, 6);
+            }
+            gl_renderer.unbind_mesh();
+        }
+
+        if !layers.is_empty() {
+            self.layer_shader.enable();
+            self.layer_shader.set_uniform_vec2(UNIFORM_LAYER_FRAMEBUFFER_SIZE_LOCATION, &framebuffer_size);
+            for layer in layers {
+                self.layer_shader.set_uniform_vec2(UNIFORM_LAYER_TEXTURE_SIZE_LOCATION, &layer.texture_size);
+                self.layer_shader.set_uniform_vec2(UNIFORM_LAYER_OFFSET_LOCATION, &layer.offset);
+                self.layer_shader.set_uniform_vec4(UNIFORM_LAYER_TINT_LOCATION, &layer.tint);
+                gl_renderer.draw_screen_quad_with_texture_id(layer.texture_id);
+            }
+        }
+    }
+}
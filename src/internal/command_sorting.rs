@@ -0,0 +1,370 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+use glam::{Mat4, Vec4};
+
+use crate::internal::renderable::Renderable;
+use crate::internal::render_target_scope::RenderTargetScope;
+use crate::internal::renderer_command::RendererCommand;
+use crate::renderer::{UNIFORM_TRANSFORMATION_MATRIX_LOCATION, UNIFORM_VIEW_MATRIX_LOCATION};
+
+struct DrawCall {
+    // Render, RenderInstanced or RenderParticles command
+    command: RendererCommand,
+    renderable: Renderable,
+    // Uniform history version when the draw was submitted, the values it expects are the ones set up to it
+    uniforms_version: usize,
+    // Material uniforms submitted right before the draw, they move together with it
+    material_uniforms: Vec<RendererCommand>,
+}
+
+// Uniform values set in the frame, in submission order per location. Draws only remember a version instead of
+// a copy of every value.
+#[derive(Clone)]
+struct UniformHistory {
+    changes: BTreeMap<i32, Vec<(usize, RendererCommand)>>,
+    version: usize,
+}
+
+impl UniformHistory {
+    fn new() -> UniformHistory {
+        UniformHistory { changes: BTreeMap::new(), version: 0 }
+    }
+
+    fn contains(&self, location: i32) -> bool {
+        self.changes.contains_key(&location)
+    }
+
+    fn set(&mut self, location: i32, command: RendererCommand) {
+        self.version += 1;
+        self.changes.entry(location).or_default().push((self.version, command));
+    }
+
+    fn get(&self, location: i32, version: usize) -> Option<&RendererCommand> {
+        let changes = self.changes.get(&location)?;
+        let count = changes.partition_point(|(change_version, _)| *change_version <= version);
+        changes[..count].last().map(|(_, command)| command)
+    }
+
+    // Value of every uniform as it was at the given version
+    fn get_all(&self, version: usize) -> impl Iterator<Item = &RendererCommand> {
+        self.changes.keys().filter_map(move |location| self.get(*location, version))
+    }
+
+    // Once no pending draw refers to older versions only the latest values are needed
+    fn compact(&mut self) {
+        for changes in self.changes.values_mut() {
+            let start = changes.len() - 1;
+            changes.drain(..start);
+        }
+    }
+}
+
+// Reorders draws so that opaque ones sharing shader, textures and mesh end up next to each other, which lets the pass
+// skip rebinding them. Translucent draws go after the opaque ones, from the farthest to the nearest. Each draw
+// still sees the uniform values that were set when it was submitted, only uniform changes that are actually
+// needed in the new order are kept.
+//
+// Clear commands and render target boundaries split the list into segments and draws never move across them.
+// A uniform that is set for the first time in the frame after some draws were already submitted does the
+// same, since there is no way to tell what value earlier draws expect from it.
+pub fn sort_commands(commands: &Vec<RendererCommand>) -> Vec<RendererCommand> {
+    let mut sorted: Vec<RendererCommand> = Vec::with_capacity(commands.len());
+    let mut uniforms = UniformHistory::new();
+    let mut applied_uniforms: HashMap<i32, RendererCommand> = HashMap::new();
+    let mut draw_calls: Vec<DrawCall> = vec![];
+    let mut material_uniforms: Vec<RendererCommand> = vec![];
+    // Uniforms of the screen while a render target is drawn, target's own values are dropped when it ends
+    let mut screen_uniforms: RenderTargetScope<(UniformHistory, HashMap<i32, RendererCommand>)> = RenderTargetScope::new();
+
+    for command in commands {
+        if let Some(renderable) = command.get_renderable() {
+            draw_calls.push(DrawCall {
+                command: *command,
+                renderable: *renderable,
+                uniforms_version: uniforms.version,
+                material_uniforms: std::mem::take(&mut material_uniforms)
+            });
+            continue;
+        }
+        if let RendererCommand::SetMaterialUniform(_, _) = command {
+            material_uniforms.push(*command);
+            continue;
+        }
+        // Sprites and background layers are drawn on their own, they don't take part in the 3D frame
+        if let RendererCommand::DrawSprite(_) | RendererCommand::DrawBackgroundLayer(_) = command {
+            continue;
+        }
+
+        match command {
+            RendererCommand::BeginRenderTarget(_) => {
+                flush_draw_calls(&mut draw_calls, &mut uniforms, &mut applied_uniforms, &mut sorted);
+                sorted.push(*command);
+                screen_uniforms.begin(|| (uniforms.clone(), applied_uniforms.clone()));
+                continue;
+            }
+            RendererCommand::EndRenderTarget() => {
+                flush_draw_calls(&mut draw_calls, &mut uniforms, &mut applied_uniforms, &mut sorted);
+                sorted.push(*command);
+                if let Some((saved_uniforms, saved_applied_uniforms)) = screen_uniforms.end() {
+                    uniforms = saved_uniforms;
+                    applied_uniforms = saved_applied_uniforms;
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        match command.uniform_location() {
+            Some(location) => {
+                if !draw_calls.is_empty() && !uniforms.contains(location) {
+                    flush_draw_calls(&mut draw_calls, &mut uniforms, &mut applied_uniforms, &mut sorted);
+                }
+                uniforms.set(location, *command);
+            }
+            None => {
+                flush_draw_calls(&mut draw_calls, &mut uniforms, &mut applied_uniforms, &mut sorted);
+                sorted.push(*command);
+            }
+        }
+    }
+    flush_draw_calls(&mut draw_calls, &mut uniforms, &mut applied_uniforms, &mut sorted);
+
+    // Uniforms set after the last draw still have to reach the shader, they carry over to the next frame
+    push_changed_uniforms(uniforms.get_all(uniforms.version), &mut applied_uniforms, &mut sorted);
+
+    sorted
+}
+
+fn flush_draw_calls(draw_calls: &mut Vec<DrawCall>, uniforms: &mut UniformHistory, applied_uniforms: &mut HashMap<i32, RendererCommand>, sorted: &mut Vec<RendererCommand>) {
+    let (mut translucent, mut opaque): (Vec<DrawCall>, Vec<DrawCall>) = draw_calls.drain(..)
+        .partition(|draw_call| draw_call.renderable.blend_mode.is_translucent());
+
+    // Stable sorts, draws with the same key keep their submission order
+    opaque.sort_by_key(|draw_call| (draw_call.renderable.program_id, draw_call.renderable.texture_ids, draw_call.renderable.mesh_id));
+
+    let mut translucent_with_depth: Vec<(f32, DrawCall)> = translucent.drain(..)
+        .map(|draw_call| (get_view_depth(&draw_call, uniforms), draw_call))
+        .collect();
+    translucent_with_depth.sort_by(|(depth_a, _), (depth_b, _)| depth_a.partial_cmp(depth_b).unwrap_or(Ordering::Equal));
+
+    for draw_call in opaque.into_iter().chain(translucent_with_depth.into_iter().map(|(_, draw_call)| draw_call)) {
+        push_changed_uniforms(uniforms.get_all(draw_call.uniforms_version), applied_uniforms, sorted);
+        sorted.extend(draw_call.material_uniforms);
+        sorted.push(draw_call.command);
+    }
+    uniforms.compact();
+}
+
+// View space z of the renderable origin. Camera looks down -z, so smaller values are farther away.
+fn get_view_depth(draw_call: &DrawCall, uniforms: &UniformHistory) -> f32 {
+    let get_matrix = |location| match uniforms.get(location, draw_call.uniforms_version) {
+        Some(RendererCommand::SetUniformMat4(_, matrix)) => *matrix,
+        _ => Mat4::IDENTITY
+    };
+    let transformation_matrix = get_matrix(UNIFORM_TRANSFORMATION_MATRIX_LOCATION);
+    let view_matrix = get_matrix(UNIFORM_VIEW_MATRIX_LOCATION);
+
+    (view_matrix * transformation_matrix * Vec4::new(0.0, 0.0, 0.0, 1.0)).z
+}
+
+fn push_changed_uniforms<'a, I>(uniforms: I, applied_uniforms: &mut HashMap<i32, RendererCommand>, sorted: &mut Vec<RendererCommand>)
+    where I: Iterator<Item = &'a RendererCommand>
+{
+    for uniform in uniforms {
+        if let Some(location) = uniform.uniform_location() {
+            if applied_uniforms.get(&location) != Some(uniform) {
+                applied_uniforms.insert(location, *uniform);
+                sorted.push(*uniform);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gl::types::GLuint;
+    use glam::{Mat4, Vec3};
+
+    use super::*;
+    use crate::internal::framebuffer::FramebufferBinding;
+    use crate::renderer::BlendMode;
+    use crate::uniform_value::UniformValue;
+
+    const UNIFORM_LOCATION: i32 = 20;
+
+    fn draw(mesh_id: GLuint, texture_id: GLuint, program_id: Option<GLuint>, blend_mode: BlendMode) -> RendererCommand {
+        RendererCommand::Render(Renderable {
+            mesh_id,
+            vertices_count: 3,
+            index_type: None,
+            program_id,
+            engine_uniform_mask: 0,
+            texture_ids: [texture_id, 0, 0, 0],
+            blend_mode
+        })
+    }
+
+    fn opaque(mesh_id: GLuint, texture_id: GLuint) -> RendererCommand {
+        draw(mesh_id, texture_id, None, BlendMode::Opaque)
+    }
+
+    fn describe(commands: &[RendererCommand]) -> Vec<String> {
+        commands.iter()
+            .map(|command| match command {
+                RendererCommand::Render(renderable) => format!("draw {}", renderable.mesh_id),
+                RendererCommand::SetUniformInt(location, value) => format!("int {} {}", location, value),
+                RendererCommand::SetUniformMat4(location, value) => format!("mat4 {} {}", location, value.w_axis.z),
+                RendererCommand::SetMaterialUniform(location, _) => format!("material {}", location),
+                RendererCommand::ClearScreen() => String::from("clear"),
+                RendererCommand::BeginRenderTarget(_) => String::from("begin target"),
+                RendererCommand::EndRenderTarget() => String::from("end target"),
+                _ => String::from("other")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn opaque_draws_are_sorted_by_program_texture_and_mesh() {
+        let commands = vec![
+            opaque(1, 2),
+            opaque(2, 1),
+            draw(3, 2, Some(5), BlendMode::Opaque),
+            opaque(4, 1),
+            opaque(0, 1),
+        ];
+
+        assert_eq!(describe(&sort_commands(&commands)), vec!["draw 0", "draw 2", "draw 4", "draw 1", "draw 3"]);
+    }
+
+    #[test]
+    fn uniform_changes_are_emitted_before_the_draws_that_saw_them() {
+        let commands = vec![
+            RendererCommand::SetUniformInt(UNIFORM_LOCATION, 1),
+            RendererCommand::SetMaterialUniform(0, UniformValue::Float(1.0)),
+            opaque(1, 2),
+            RendererCommand::SetUniformInt(UNIFORM_LOCATION, 2),
+            opaque(2, 1),
+            opaque(3, 1),
+        ];
+
+        // Value set last is put back after the draws, next frame starts from it
+        assert_eq!(describe(&sort_commands(&commands)), vec![
+            "int 20 2", "draw 2", "draw 3",
+            "int 20 1", "material 0", "draw 1",
+            "int 20 2"
+        ]);
+    }
+
+    #[test]
+    fn unchanged_uniforms_are_not_emitted_again() {
+        let commands = vec![
+            RendererCommand::SetUniformInt(UNIFORM_LOCATION, 1),
+            opaque(1, 1),
+            RendererCommand::SetUniformInt(UNIFORM_LOCATION, 1),
+            opaque(2, 1),
+        ];
+
+        assert_eq!(describe(&sort_commands(&commands)), vec!["int 20 1", "draw 1", "draw 2"]);
+    }
+
+    #[test]
+    fn draws_never_move_across_clears() {
+        let commands = vec![
+            opaque(2, 2),
+            opaque(1, 1),
+            RendererCommand::ClearScreen(),
+            opaque(4, 2),
+            opaque(3, 1),
+        ];
+
+        assert_eq!(describe(&sort_commands(&commands)), vec!["draw 1", "draw 2", "clear", "draw 3", "draw 4"]);
+    }
+
+    #[test]
+    fn uniform_first_set_after_draws_splits_the_segment() {
+        let commands = vec![
+            opaque(2, 2),
+            RendererCommand::SetUniformInt(UNIFORM_LOCATION, 1),
+            opaque(1, 1),
+        ];
+
+        assert_eq!(describe(&sort_commands(&commands)), vec!["draw 2", "int 20 1", "draw 1"]);
+    }
+
+    #[test]
+    fn translucent_draws_follow_opaque_ones_from_far_to_near() {
+        let at_depth = |z: f32| RendererCommand::SetUniformMat4(UNIFORM_TRANSFORMATION_MATRIX_LOCATION, Mat4::from_translation(Vec3::new(0.0, 0.0, z)));
+        let commands = vec![
+            at_depth(-1.0),
+            draw(1, 1, None, BlendMode::Alpha),
+            at_depth(-5.0),
+            draw(2, 1, None, BlendMode::Additive),
+            at_depth(-3.0),
+            opaque(3, 1),
+        ];
+
+        let draws: Vec<String> = describe(&sort_commands(&commands)).into_iter()
+            .filter(|command| command.starts_with("draw"))
+            .collect();
+        assert_eq!(draws, vec!["draw 3", "draw 2", "draw 1"]);
+    }
+
+    #[test]
+    fn render_target_uniforms_dont_reach_the_screen() {
+        let binding = FramebufferBinding { framebuffer_object: 1, width: 64, height: 64 };
+        let commands = vec![
+            RendererCommand::SetUniformInt(UNIFORM_LOCATION, 1),
+            opaque(2, 2),
+            RendererCommand::BeginRenderTarget(binding),
+            RendererCommand::SetUniformInt(UNIFORM_LOCATION, 2),
+            opaque(4, 2),
+            opaque(3, 1),
+            RendererCommand::EndRenderTarget(),
+            opaque(1, 1),
+        ];
+
+        // Main pass puts the screen values back itself when the target ends, nothing is emitted for them
+        assert_eq!(describe(&sort_commands(&commands)), vec![
+            "int 20 1", "draw 2",
+            "begin target", "int 20 2", "draw 3", "draw 4", "end target",
+            "draw 1"
+        ]);
+    }
+
+    #[test]
+    fn uniform_history_returns_values_as_they_were_at_a_version() {
+        let mut history = UniformHistory::new();
+        history.set(UNIFORM_LOCATION, RendererCommand::SetUniformInt(UNIFORM_LOCATION, 1));
+        let first_version = history.version;
+        history.set(UNIFORM_LOCATION, RendererCommand::SetUniformInt(UNIFORM_LOCATION, 2));
+
+        assert!(history.get(UNIFORM_LOCATION, 0).is_none());
+        assert!(history.get(UNIFORM_LOCATION, first_version) == Some(&RendererCommand::SetUniformInt(UNIFORM_LOCATION, 1)));
+        assert!(history.get(UNIFORM_LOCATION, history.version) == Some(&RendererCommand::SetUniformInt(UNIFORM_LOCATION, 2)));
+
+        history.compact();
+        assert!(history.get(UNIFORM_LOCATION, history.version) == Some(&RendererCommand::SetUniformInt(UNIFORM_LOCATION, 2)));
+        assert_eq!(history.changes[&UNIFORM_LOCATION].len(), 1);
+    }
+}
@@ -17,22 +17,57 @@
 // along with RSFX. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use std::ffi::c_void;
 use std::ptr::null;
+use std::rc::Rc;
 
 use gl::types::{GLuint, GLint};
 
+use crate::error::Error;
+use crate::internal::render_backend::RenderBackend;
 use crate::texture::Texture;
 
+// Framebuffer to draw into, without owning it
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct FramebufferBinding {
+    pub framebuffer_object: GLuint,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl FramebufferBinding {
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer_object);
+        }
+    }
+}
+
 pub struct Framebuffer {
     width: i32,
     height: i32,
     framebuffer_object: GLuint,
     depth_buffer: GLuint,
-    texture: Texture,
+    // Shared so materials can keep using it, the texture outlives the framebuffer if they do
+    texture: Rc<Texture>,
+    // Created in a headless run, there are no GL objects behind the ids
+    placeholder: bool,
 }
 
 impl Framebuffer {
-    pub fn new(width: i32, height: i32) -> Framebuffer {
+    pub fn new(width: i32, height: i32) -> Result<Framebuffer, Error> {
+        // Headless run, render targets only need an id
+        if RenderBackend::is_headless() {
+            return Ok(Framebuffer {
+                width,
+                height,
+                framebuffer_object: RenderBackend::next_placeholder_id(),
+                depth_buffer: 0,
+                texture: Rc::new(Texture::placeholder(width as GLuint, height as GLuint)),
+                placeholder: true
+            });
+        }
+
         unsafe {
             let framebuffer_object = {
                 let mut fbos = vec![0];
@@ -67,19 +102,25 @@ impl Framebuffer {
             
             gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, depth_buffer);
             
-            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
-                panic!("Failed to create frame buffer!");
-            }
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
             
             gl::BindFramebuffer(gl::FRAMEBUFFER, 0);  
             
-            Framebuffer {
+            let framebuffer = Framebuffer {
                 width,
                 height,
                 framebuffer_object,
-                texture: Texture::new(texture, width as GLuint, height as GLuint),
-                depth_buffer
+                texture: Rc::new(Texture::new(texture, width as GLuint, height as GLuint)),
+                depth_buffer,
+                placeholder: false
+            };
+
+            // Framebuffer is dropped here on failure, releasing what was already created
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(Error::GlInit(format!("Failed to create {}x{} frame buffer, status: {:#x}", width, height, status)));
             }
+
+            Ok(framebuffer)
         }
     }
     
@@ -95,7 +136,27 @@ impl Framebuffer {
         }
     }
     
-    pub fn get_texture(&self) -> &Texture {
+    // RGBA pixels of the color attachment, rows from top to bottom
+    pub fn read_pixels(&self) -> Vec<u8> {
+        let row_size = self.width as usize * 4;
+        let mut pixels: Vec<u8> = vec![0; row_size * self.height as usize];
+
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.framebuffer_object);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(0, 0, self.width, self.height, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut c_void);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        }
+
+        // GL rows start at the bottom
+        pixels.chunks_exact(row_size).rev().flatten().copied().collect()
+    }
+
+    pub fn get_binding(&self) -> FramebufferBinding {
+        FramebufferBinding { framebuffer_object: self.framebuffer_object, width: self.width, height: self.height }
+    }
+
+    pub fn get_texture(&self) -> &Rc<Texture> {
         &self.texture
     }
     
@@ -110,6 +171,10 @@ impl Framebuffer {
 
 impl Drop for Framebuffer {
     fn drop(&mut self) {
+        if self.placeholder {
+            return;
+        }
+
         unsafe {
             let rbos_vec = vec![self.depth_buffer];
             gl::DeleteRenderbuffers(rbos_vec.len() as gl::types::GLsizei, rbos_vec.as_ptr());
@@ -18,35 +18,56 @@
 //
 
 use std::cmp;
-use std::ffi::CString;
+use std::ffi::{c_void, CString};
+use std::mem;
+use std::ptr::null;
 
 use gl;
-use gl::types::{GLenum, GLsizei, GLuint};
-use glam::{Vec3, Vec2};
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use glam::{Mat4, Vec3, Vec2, Vec4};
+use crate::internal::background_renderer::BackgroundRenderer;
+use crate::internal::render_passes::dither_pass::DitherPass;
 use crate::internal::render_passes::main_pass::MainPass;
+use crate::internal::render_passes::overlay_pass::OverlayPass;
+use crate::internal::render_passes::post_process_pass::{PostProcessPass, PostProcessPassSlot};
 use crate::internal::render_passes::RenderPass;
 use crate::internal::renderer_command::RendererCommand;
 
+use crate::error::{Error, ShaderStage};
+use crate::renderer::BlendMode;
+use crate::screenshot::{Screenshot, ScreenshotSource};
 use crate::internal::shader_program::ShaderProgram;
 use crate::mesh::Mesh;
 use crate::texture::Texture;
 use super::aspect_ratio::AspectRatio;
 use super::framebuffer::Framebuffer;
+use super::vertex_attributes;
+use crate::graphics_settings::{GraphicsSettings, VertexSnapping};
+use crate::material::MaterialShader;
+use crate::post_process::PostProcessPassId;
+use crate::uniform_value::UniformValue;
 use crate::vertex_data::VertexData;
 
 pub struct GlRenderer {
     render_passes: Vec<RenderPass>,
+    // Run after built-in passes, kept sorted by order
+    post_process_passes: Vec<PostProcessPassSlot>,
     screen_quad: Mesh,
+    // Particle vertices of the current frame
+    particle_mesh: Mesh,
     screen_shader: ShaderProgram,
     window_width: i32,
     window_height: i32,
     window_aspect_ratio: AspectRatio,
     framebuffer_width: i32,
     framebuffer_height: i32,
+    instance_buffer: GLuint,
+    // Settings passes depend on, window related ones are applied elsewhere
+    graphics_settings: GraphicsSettings,
 }
 
 impl GlRenderer {
-    pub fn new(framebuffer_width: i32, framebuffer_height: i32, window_width: i32, window_height: i32) -> GlRenderer {
+    pub fn new(framebuffer_width: i32, framebuffer_height: i32, window_width: i32, window_height: i32) -> Result<GlRenderer, Error> {
         unsafe {
             // Enable back face culling
             gl::Enable(gl::CULL_FACE);
@@ -61,20 +82,52 @@ impl GlRenderer {
 
         // Main renderer
         {
-            let framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
-            let frambuffer_aspect_ratio = AspectRatio::from(framebuffer_width, framebuffer_height);
-            if frambuffer_aspect_ratio != AspectRatio::R16by9 {
-                panic!("Unexpected framebuffer aspect ratio! Expecting framebuffer resolution to be 16:9");
-            }
+            GlRenderer::check_framebuffer_aspect_ratio(framebuffer_width, framebuffer_height)?;
+            let framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height)?;
 
             let shader = {
-                ShaderProgram::load_shaders(
+                ShaderProgram::load_shaders_with_geometry(
                     &CString::new(include_str!("shaders/main_pass_shader.vert")).unwrap(),
+                    &CString::new(include_str!("shaders/main_pass_shader.geom")).unwrap(),
                     &CString::new(include_str!("shaders/main_pass_shader.frag")).unwrap(),
-                )
+                )?
+            };
+
+            let pass_steps = Box::new(MainPass::new(BackgroundRenderer::new()?));
+
+            let render_pass = RenderPass::new(framebuffer, shader, pass_steps);
+            render_passes.push(render_pass);
+        }
+
+        // Sprites and HUD over the 3D scene
+        {
+            let framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height)?;
+
+            let shader = {
+                ShaderProgram::load_shaders(
+                    &CString::new(include_str!("shaders/overlay_pass_shader.vert")).unwrap(),
+                    &CString::new(include_str!("shaders/overlay_pass_shader.frag")).unwrap(),
+                )?
+            };
+
+            let pass_steps = Box::new(OverlayPass::new());
+
+            let render_pass = RenderPass::new(framebuffer, shader, pass_steps);
+            render_passes.push(render_pass);
+        }
+
+        // Color depth reduction
+        {
+            let framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height)?;
+
+            let shader = {
+                ShaderProgram::load_shaders(
+                    &CString::new(include_str!("shaders/screen_shader.vert")).unwrap(),
+                    &CString::new(include_str!("shaders/dither_pass_shader.frag")).unwrap(),
+                )?
             };
 
-            let pass_steps = Box::new(MainPass::new());
+            let pass_steps = Box::new(DitherPass::new());
 
             let render_pass = RenderPass::new(framebuffer, shader, pass_steps);
             render_passes.push(render_pass);
@@ -84,7 +137,7 @@ impl GlRenderer {
             ShaderProgram::load_shaders(
                 &CString::new(include_str!("shaders/screen_shader.vert")).unwrap(),
                 &CString::new(include_str!("shaders/screen_shader.frag")).unwrap(),
-            )
+            )?
         };
 
         let quad_data: Vec<VertexData> = vec![
@@ -99,18 +152,188 @@ impl GlRenderer {
         
         let window_aspect_ratio = AspectRatio::from(window_width, window_height);
 
-        GlRenderer {
+        let instance_buffer = {
+            let mut buffer_ids = vec![0];
+            unsafe {
+                gl::GenBuffers(buffer_ids.len() as GLsizei, buffer_ids.as_mut_ptr());
+            }
+            buffer_ids[0]
+        };
+        GlRenderer::reset_instance_transformation_attribute();
+        // Meshes without vertex colors have the attribute disabled and read this constant instead
+        unsafe {
+            gl::VertexAttrib4f(vertex_attributes::VERTEX_COLOR_ATTRIBUTE_ID, 1.0, 1.0, 1.0, 1.0);
+        }
+
+        Ok(GlRenderer {
             render_passes,
+            post_process_passes: vec![],
             screen_quad,
+            particle_mesh: Mesh::from_raw_data(&vec![]),
             screen_shader,
             window_width,
             window_height,
             window_aspect_ratio,
             framebuffer_width,
-            framebuffer_height
+            framebuffer_height,
+            instance_buffer,
+            graphics_settings: GraphicsSettings::new()
+        })
+    }
+
+    // Rebuilds render pass framebuffers for the new resolution, viewport follows the new window size
+    pub fn resize(&mut self, framebuffer_width: i32, framebuffer_height: i32, window_width: i32, window_height: i32) -> Result<(), Error> {
+        GlRenderer::check_framebuffer_aspect_ratio(framebuffer_width, framebuffer_height)?;
+
+        if framebuffer_width != self.framebuffer_width || framebuffer_height != self.framebuffer_height {
+            // Size is updated first, so that resizing back after a failure goes through every pass again
+            self.framebuffer_width = framebuffer_width;
+            self.framebuffer_height = framebuffer_height;
+            for render_pass in &mut self.render_passes {
+                render_pass.resize(framebuffer_width, framebuffer_height)?;
+            }
+            for post_process_pass in &mut self.post_process_passes {
+                post_process_pass.render_pass.resize(framebuffer_width, framebuffer_height)?;
+            }
+        }
+
+        self.window_width = window_width;
+        self.window_height = window_height;
+        self.window_aspect_ratio = AspectRatio::from(window_width, window_height);
+
+        Ok(())
+    }
+
+    pub fn add_post_process_pass(&mut self, id: PostProcessPassId, fragment_shader_source: &str, order: i32) -> Result<(), Error> {
+        let shader = ShaderProgram::load_shaders(
+            &CString::new(include_str!("shaders/screen_shader.vert")).unwrap(),
+            &GlRenderer::to_shader_source(fragment_shader_source, ShaderStage::Fragment)?,
+        )?;
+        let framebuffer = Framebuffer::new(self.framebuffer_width, self.framebuffer_height)?;
+
+        let pass_steps = Box::new(PostProcessPass::new(&shader));
+        let render_pass = RenderPass::new(framebuffer, shader, pass_steps);
+
+        self.post_process_passes.push(PostProcessPassSlot { id, order, enabled: true, render_pass });
+        self.sort_post_process_passes();
+        Ok(())
+    }
+
+    pub fn create_material_shader(&self, vertex_shader_source: Option<&str>, fragment_shader_source: &str) -> Result<MaterialShader, Error> {
+        let fragment_shader_source = GlRenderer::to_shader_source(fragment_shader_source, ShaderStage::Fragment)?;
+        let program = match vertex_shader_source {
+            Some(vertex_shader_source) => ShaderProgram::load_shaders(
+                &GlRenderer::to_shader_source(vertex_shader_source, ShaderStage::Vertex)?,
+                &fragment_shader_source,
+            )?,
+            None => ShaderProgram::load_shaders_with_geometry(
+                &CString::new(include_str!("shaders/main_pass_shader.vert")).unwrap(),
+                &CString::new(include_str!("shaders/main_pass_shader.geom")).unwrap(),
+                &fragment_shader_source,
+            )?
+        };
+
+        // Engine uniform is only set in the program when it has the same name at the same location as in
+        // the built-in shader, anything else there belongs to the material
+        let built_in_shader = self.render_passes[0].get_shader();
+        let mut engine_uniform_mask: u64 = 0;
+        for location in 0..64 {
+            if let Some(name) = built_in_shader.get_uniform_name(location) {
+                if program.get_uniform_name(location) == Some(name) {
+                    engine_uniform_mask |= 1 << location;
+                }
+            }
+        }
+
+        Ok(MaterialShader::new(program, engine_uniform_mask))
+    }
+
+    fn to_shader_source(source: &str, stage: ShaderStage) -> Result<CString, Error> {
+        CString::new(source)
+            .map_err(|_| Error::ShaderCompile { stage, info_log: String::from("Shader source contains a nul character") })
+    }
+
+    pub fn remove_post_process_pass(&mut self, id: PostProcessPassId) {
+        self.post_process_passes.retain(|post_process_pass| post_process_pass.id != id);
+    }
+
+    pub fn set_post_process_pass_enabled(&mut self, id: PostProcessPassId, enabled: bool) {
+        if let Some(post_process_pass) = self.get_post_process_pass_mut(id) {
+            post_process_pass.enabled = enabled;
+        }
+    }
+
+    pub fn set_post_process_pass_order(&mut self, id: PostProcessPassId, order: i32) {
+        if let Some(post_process_pass) = self.get_post_process_pass_mut(id) {
+            post_process_pass.order = order;
+        }
+        self.sort_post_process_passes();
+    }
+
+    // Program keeps the value, it doesn't have to be set every frame
+    pub fn set_post_process_uniform(&mut self, id: PostProcessPassId, name: &str, value: &UniformValue) {
+        if let Some(post_process_pass) = self.get_post_process_pass_mut(id) {
+            let shader = post_process_pass.render_pass.get_shader();
+            let location = shader.get_uniform_location(name);
+            if location >= 0 {
+                shader.enable();
+                shader.set_uniform(location, value);
+                shader.disable();
+            }
+        }
+    }
+
+    fn get_post_process_pass_mut(&mut self, id: PostProcessPassId) -> Option<&mut PostProcessPassSlot> {
+        self.post_process_passes.iter_mut().find(|post_process_pass| post_process_pass.id == id)
+    }
+
+    // Stable, passes with the same order run in the order they were added
+    fn sort_post_process_passes(&mut self) {
+        self.post_process_passes.sort_by_key(|post_process_pass| post_process_pass.order);
+    }
+
+    // Enabled passes in the order they run, each one gets result of the previous one
+    fn get_active_passes(&self) -> Vec<&RenderPass> {
+        let built_in_passes = self.render_passes.iter()
+            .filter(|render_pass| render_pass.is_enabled(self));
+        let post_process_passes = self.post_process_passes.iter()
+            .filter(|post_process_pass| post_process_pass.enabled)
+            .map(|post_process_pass| &post_process_pass.render_pass);
+
+        built_in_passes.chain(post_process_passes).collect()
+    }
+
+    pub fn set_graphics_settings(&mut self, graphics_settings: &GraphicsSettings) {
+        self.graphics_settings = *graphics_settings;
+    }
+
+    pub fn get_graphics_settings(&self) -> &GraphicsSettings {
+        &self.graphics_settings
+    }
+
+    // Snap grid size in cells, zero when snapping is off, and depth at which the grid gets half as fine,
+    // zero when it doesn't depend on depth. Native grid is the size of the framebuffer drawn into.
+    pub fn get_vertex_snapping_grid(&self, framebuffer_size: Vec2) -> (Vec2, f32) {
+        let native_grid = framebuffer_size;
+        match self.graphics_settings.vertex_snapping {
+            VertexSnapping::Off => (Vec2::ZERO, 0.0),
+            VertexSnapping::Native => (native_grid, 0.0),
+            VertexSnapping::Custom { width, height } => (Vec2::new(width.max(1) as f32, height.max(1) as f32), 0.0),
+            VertexSnapping::DepthDependent { halving_distance } => (native_grid, halving_distance.max(0.0)),
         }
     }
 
+    fn check_framebuffer_aspect_ratio(framebuffer_width: i32, framebuffer_height: i32) -> Result<(), Error> {
+        if AspectRatio::from(framebuffer_width, framebuffer_height) != AspectRatio::R16by9 {
+            return Err(Error::GlInit(format!(
+                "Unexpected framebuffer aspect ratio of {}x{}! Expecting framebuffer resolution to be 16:9",
+                framebuffer_width,
+                framebuffer_height
+            )));
+        }
+        Ok(())
+    }
+
     pub fn clear_buffer(&self) {
         unsafe {
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
@@ -129,10 +352,64 @@ impl GlRenderer {
         }
     }
 
-    pub fn run_passes(&self, commands: &Vec<RendererCommand>) {
+    pub fn set_face_culling(&self, enabled: bool) {
+        unsafe {
+            if enabled {
+                gl::Enable(gl::CULL_FACE);
+            } else {
+                gl::Disable(gl::CULL_FACE);
+            }
+        }
+    }
+
+    pub fn set_blend_mode(&self, blend_mode: BlendMode) {
+        unsafe {
+            match blend_mode {
+                BlendMode::Opaque | BlendMode::Alpha => {
+                    gl::BlendEquation(gl::FUNC_ADD);
+                    gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+                }
+                BlendMode::Additive => {
+                    gl::BlendEquation(gl::FUNC_ADD);
+                    gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+                }
+                BlendMode::Subtractive => {
+                    gl::BlendEquation(gl::FUNC_REVERSE_SUBTRACT);
+                    gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+                }
+                BlendMode::Average => {
+                    gl::BlendEquation(gl::FUNC_ADD);
+                    gl::BlendColor(0.5, 0.5, 0.5, 0.5);
+                    gl::BlendFunc(gl::CONSTANT_COLOR, gl::ONE_MINUS_CONSTANT_COLOR);
+                }
+            }
+
+            // Translucent surfaces must not hide what is drawn behind them after
+            gl::DepthMask(if blend_mode.is_translucent() { gl::FALSE } else { gl::TRUE });
+        }
+    }
+
+    pub fn run_passes(&mut self, commands: &Vec<RendererCommand>, instance_transforms: &[Mat4], particle_vertices: &[VertexData], particle_colors: &[Vec4]) {
+        if !instance_transforms.is_empty() {
+            unsafe {
+                gl::BindBuffer(gl::ARRAY_BUFFER, self.instance_buffer);
+                gl::BufferData(
+                    gl::ARRAY_BUFFER,
+                    mem::size_of_val(instance_transforms) as isize,
+                    instance_transforms.as_ptr() as *const c_void,
+                    gl::STREAM_DRAW
+                );
+                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            }
+        }
+        // Uploaded once for the whole frame, draws of the same emitter keep their own vertices
+        if !particle_vertices.is_empty() {
+            self.particle_mesh.update_vertex_data(particle_vertices, particle_colors);
+        }
+
         let mut last_pass_result = None;
 
-        for render_pass in &self.render_passes {
+        for render_pass in self.get_active_passes() {
             render_pass.on_execute(&self, commands, &last_pass_result);
             last_pass_result = Some(render_pass.get_pass_result());
         }
@@ -143,6 +420,41 @@ impl GlRenderer {
         self.render_mesh_with_one_textures(&self.screen_quad, last_pass_result.unwrap());
     }
 
+    // Reads back the last frame. Window contents are gone after the buffer swap, so the final
+    // upscale is drawn once more into an offscreen framebuffer of window size.
+    pub fn capture_screenshot(&self, source: ScreenshotSource) -> Result<Screenshot, Error> {
+        let last_pass = match self.get_active_passes().last().copied() {
+            Some(render_pass) => render_pass,
+            None => return Err(Error::Unsupported(String::from("There are no render passes to capture")))
+        };
+
+        let screenshot = match source {
+            ScreenshotSource::Framebuffer => {
+                let framebuffer = last_pass.get_framebuffer();
+                Screenshot::new(framebuffer.get_width() as u32, framebuffer.get_height() as u32, framebuffer.read_pixels())
+            }
+            ScreenshotSource::Window => {
+                let window_framebuffer = Framebuffer::new(self.window_width, self.window_height)?;
+                window_framebuffer.bind();
+                // Letterbox bars are black, clearing this way leaves clear color set by scenes untouched
+                let black: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+                unsafe {
+                    gl::ClearBufferfv(gl::COLOR, 0, black.as_ptr());
+                }
+
+                self.screen_shader.enable();
+                self.disable_depth_test();
+                self.set_framebuffer_viewport_for_window();
+                self.render_mesh_with_one_textures(&self.screen_quad, last_pass.get_pass_result());
+                window_framebuffer.unbind();
+
+                Screenshot::new(self.window_width as u32, self.window_height as u32, window_framebuffer.read_pixels())
+            }
+        };
+
+        Ok(screenshot)
+    }
+
     pub fn set_clear_color(&self, r: f32, g: f32, b: f32) {
         let real_r = (r % 255.0) / 255.0;
         let real_g = (g % 255.0) / 255.0;
@@ -178,10 +490,102 @@ impl GlRenderer {
         }
     }
 
+    pub fn draw_arrays_range(&self, first_vertex: GLint, vertices_count: GLsizei) {
+        unsafe {
+            gl::DrawArrays(gl::TRIANGLES, first_vertex, vertices_count);
+        }
+    }
+
+    pub fn draw_elements(&self, indices_count: GLsizei, index_type: GLenum) {
+        unsafe {
+            gl::DrawElements(gl::TRIANGLES, indices_count, index_type, null());
+        }
+    }
+
+    // Draws currently bound mesh once per instance, using transforms uploaded for this frame
+    pub fn draw_instanced(&self, vertices_count: GLsizei, index_type: Option<GLenum>, first_instance: GLuint, instance_count: GLsizei) {
+        unsafe {
+            // Attribute setup is stored in the bound vertex array, it's pointed to the instance buffer
+            // only for this draw and disabled again after it
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.instance_buffer);
+            for column in 0..vertex_attributes::VERTEX_INSTANCE_TRANSFORMATION_COLUMNS {
+                let attribute_id = vertex_attributes::VERTEX_INSTANCE_TRANSFORMATION_ATTRIBUTE_ID + column;
+                gl::EnableVertexAttribArray(attribute_id);
+                gl::VertexAttribPointer(
+                    attribute_id,
+                    4,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    mem::size_of::<Mat4>() as GLsizei,
+                    (column as usize * mem::size_of::<Vec4>()) as _
+                );
+                gl::VertexAttribDivisor(attribute_id, 1);
+            }
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            match index_type {
+                Some(index_type) => {
+                    gl::DrawElementsInstancedBaseInstance(gl::TRIANGLES, vertices_count, index_type, null(), instance_count, first_instance);
+                }
+                None => {
+                    gl::DrawArraysInstancedBaseInstance(gl::TRIANGLES, 0, vertices_count, instance_count, first_instance);
+                }
+            }
+
+            for column in 0..vertex_attributes::VERTEX_INSTANCE_TRANSFORMATION_COLUMNS {
+                gl::DisableVertexAttribArray(vertex_attributes::VERTEX_INSTANCE_TRANSFORMATION_ATTRIBUTE_ID + column);
+            }
+        }
+        GlRenderer::reset_instance_transformation_attribute();
+    }
+
+    // Without an attribute array the shader reads the constant value, identity keeps regular draws unaffected
+    fn reset_instance_transformation_attribute() {
+        let identity = Mat4::IDENTITY;
+        for column in 0..vertex_attributes::VERTEX_INSTANCE_TRANSFORMATION_COLUMNS {
+            let value = identity.col(column as usize);
+            unsafe {
+                gl::VertexAttrib4f(vertex_attributes::VERTEX_INSTANCE_TRANSFORMATION_ATTRIBUTE_ID + column, value.x, value.y, value.z, value.w);
+            }
+        }
+    }
+
+    // Draws currently bound mesh, through its element buffer if it has one
+    pub fn draw(&self, vertices_count: GLsizei, index_type: Option<GLenum>) {
+        match index_type {
+            Some(index_type) => self.draw_elements(vertices_count, index_type),
+            None => self.draw_arrays(vertices_count)
+        }
+    }
+
+    // Vertex array holding this frame's particle batch, uploaded at the start of run_passes
+    pub fn get_particle_mesh_id(&self) -> GLuint {
+        self.particle_mesh.vao_id()
+    }
+
+    // Full-screen quad for post-process passes
+    pub fn draw_screen_quad(&self, texture: &Texture) {
+        self.render_mesh_with_one_textures(&self.screen_quad, texture);
+    }
+
+    // Copies the texture over the whole bound framebuffer
+    pub fn draw_screen_texture(&self, texture: &Texture) {
+        self.screen_shader.enable();
+        self.draw_screen_quad(texture);
+    }
+
+    // For textures that are not owned here, like the ones background layers point to
+    pub fn draw_screen_quad_with_texture_id(&self, texture_id: GLuint) {
+        self.bind_mesh(self.screen_quad.vao_id());
+        self.bind_texture(texture_id, gl::TEXTURE0);
+        self.draw(self.screen_quad.vertices_count(), self.screen_quad.index_type());
+        self.unbind_mesh();
+    }
+
     pub fn render_mesh_with_one_textures(&self, mesh: &Mesh, texture: &Texture) {
         self.bind_mesh(mesh.vao_id());
         self.bind_texture(texture.texture_id(), gl::TEXTURE0);
-        self.draw_arrays(mesh.vertices_count());
+        self.draw(mesh.vertices_count(), mesh.index_type());
         self.unbind_mesh();
     }
 
@@ -189,7 +593,7 @@ impl GlRenderer {
         self.bind_mesh(mesh.vao_id());
         self.bind_texture(texture_1.texture_id(), gl::TEXTURE0);
         self.bind_texture(texture_2.texture_id(), gl::TEXTURE1);
-        self.draw_arrays(mesh.vertices_count());
+        self.draw(mesh.vertices_count(), mesh.index_type());
         self.unbind_mesh();
     }
 
@@ -228,3 +632,12 @@ impl GlRenderer {
         }
     }
 }
+
+impl Drop for GlRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            let buffers = [self.instance_buffer];
+            gl::DeleteBuffers(buffers.len() as GLsizei, buffers.as_ptr());
+        }
+    }
+}
@@ -17,15 +17,21 @@
 // along with RSFX. If not, see <https://www.gnu.org/licenses/>.
 //
 
-pub(crate) mod time;
 pub(crate) mod byte_buffer_reader;
 pub(crate) mod gl_renderer;
 pub(crate) mod window_context;
+pub(crate) mod window_backend;
+pub(crate) mod null_window;
 pub(crate) mod shader_program;
 pub(crate) mod framebuffer;
 pub(crate) mod aspect_ratio;
 pub(crate) mod render_passes;
 pub(crate) mod renderable;
 pub(crate) mod renderer_command;
+pub(crate) mod render_backend;
+pub(crate) mod command_sorting;
 pub(crate) mod vertex_attributes;
+pub(crate) mod overlay_sprite;
+pub(crate) mod background_renderer;
+pub(crate) mod render_target_scope;
 
@@ -0,0 +1,61 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use crate::error::Error;
+use crate::graphics_settings::GraphicsSettings;
+use crate::input::Input;
+use crate::internal::window_backend::WindowBackend;
+
+// Window stand-in for headless runs. Instead of polling SDL events it hands the input over
+// to a script, once per frame, together with the index of that frame
+pub struct NullWindow<F: FnMut(u64, &mut Input)> {
+    frame: u64,
+    display_width: i32,
+    display_height: i32,
+    input_script: F,
+}
+
+impl<F: FnMut(u64, &mut Input)> NullWindow<F> {
+    pub fn new(display_width: i32, display_height: i32, input_script: F) -> NullWindow<F> {
+        NullWindow { frame: 0, display_width, display_height, input_script }
+    }
+}
+
+impl<F: FnMut(u64, &mut Input)> WindowBackend for NullWindow<F> {
+    fn poll_input_events(&mut self, input: &mut Input) {
+        (self.input_script)(self.frame, input);
+        self.frame += 1;
+    }
+
+    fn swap_buffer(&mut self) { }
+
+    fn apply_graphics_settings(&mut self, _graphics_settings: &GraphicsSettings) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_display_size(&self) -> (i32, i32) {
+        (self.display_width, self.display_height)
+    }
+
+    // There is no window, pretend it covers the whole display
+    fn get_window_size(&self) -> (i32, i32) {
+        (self.display_width, self.display_height)
+    }
+}
@@ -0,0 +1,79 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+use gl::types::GLuint;
+use glam::{Vec2, Vec4};
+use crate::sprite::Sprite;
+use crate::texture::Texture;
+
+// Sprite as the overlay pass draws it
+#[derive(Copy, Clone, PartialEq)]
+pub struct OverlaySprite {
+    // 0 for untextured quads
+    pub texture_id: GLuint,
+    pub position: Vec2,
+    pub size: Vec2,
+    // Texture coordinates of the bottom left and the top right corner
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+    pub tint: Vec4,
+    pub rotation: f32,
+    pub layer: i32,
+}
+
+impl OverlaySprite {
+    pub fn new(sprite: &Sprite, texture: Option<&Texture>) -> OverlaySprite {
+        let (texture_id, uv_min, uv_max) = match (texture, sprite.source_rect) {
+            (Some(texture), Some(source_rect)) => {
+                // Image rows are stored bottom up, source rect y counts from the top
+                let texture_size = Vec2::new(texture.width().max(1) as f32, texture.height().max(1) as f32);
+                let uv_min = Vec2::new(source_rect.x, texture_size.y - source_rect.y - source_rect.height) / texture_size;
+                let uv_max = Vec2::new(source_rect.x + source_rect.width, texture_size.y - source_rect.y) / texture_size;
+                (texture.texture_id(), uv_min, uv_max)
+            }
+            (Some(texture), None) => (texture.texture_id(), Vec2::ZERO, Vec2::ONE),
+            (None, _) => (0, Vec2::ZERO, Vec2::ONE),
+        };
+
+        OverlaySprite {
+            texture_id,
+            position: sprite.position,
+            size: sprite.size,
+            uv_min,
+            uv_max,
+            tint: sprite.tint,
+            rotation: sprite.rotation,
+            layer: sprite.layer
+        }
+    }
+
+    // Corners in framebuffer pixels with their texture coordinates, counter-clockwise from the bottom left
+    pub fn get_corners(&self) -> [(Vec2, Vec2); 4] {
+        let half_size = self.size / 2.0;
+        let rotation = Vec2::from_angle(self.rotation);
+        let corner = |offset: Vec2, uv: Vec2| (self.position + rotation.rotate(offset * half_size), uv);
+
+        [
+            corner(Vec2::new(-1.0, -1.0), self.uv_min),
+            corner(Vec2::new( 1.0, -1.0), Vec2::new(self.uv_max.x, self.uv_min.y)),
+            corner(Vec2::new( 1.0,  1.0), self.uv_max),
+            corner(Vec2::new(-1.0,  1.0), Vec2::new(self.uv_min.x, self.uv_max.y)),
+        ]
+    }
+}
@@ -0,0 +1,142 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use gl::types::GLuint;
+use glam::{Mat4, Vec4};
+
+use crate::error::Error;
+use crate::headless::RecordedFrame;
+use crate::graphics_settings::GraphicsSettings;
+use crate::material::MaterialShader;
+use crate::post_process::PostProcessPassId;
+use crate::uniform_value::UniformValue;
+use crate::internal::gl_renderer::GlRenderer;
+use crate::internal::renderer_command::RendererCommand;
+use crate::screenshot::{Screenshot, ScreenshotSource};
+use crate::vertex_data::VertexData;
+
+// Ids of resources created without a GL context, they only need to tell resources apart
+static NEXT_PLACEHOLDER_ID: AtomicU32 = AtomicU32::new(1);
+
+thread_local! {
+    // Set while a headless run is going on this thread
+    static HEADLESS: Cell<bool> = const { Cell::new(false) };
+}
+
+// Marks the thread as running headless until dropped
+pub struct HeadlessScope {
+    was_headless: bool,
+}
+
+pub enum RenderBackend {
+    Gl(Box<GlRenderer>),
+    // Headless mode, nothing is drawn and every frame is kept for inspection
+    Recording(Vec<RecordedFrame>),
+}
+
+impl RenderBackend {
+    pub fn enter_headless() -> HeadlessScope {
+        HeadlessScope { was_headless: HEADLESS.replace(true) }
+    }
+
+    // Meshes, textures and render targets created in headless runs get placeholder ids instead of GL objects
+    pub fn is_headless() -> bool {
+        HEADLESS.get()
+    }
+
+    pub fn next_placeholder_id() -> GLuint {
+        NEXT_PLACEHOLDER_ID.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn run_passes(&mut self, commands: &Vec<RendererCommand>, instance_transforms: &[Mat4], particle_vertices: &[VertexData], particle_colors: &[Vec4]) {
+        match self {
+            RenderBackend::Gl(gl_renderer) => gl_renderer.run_passes(commands, instance_transforms, particle_vertices, particle_colors),
+            RenderBackend::Recording(frames) => frames.push(RecordedFrame::new(commands.clone(), instance_transforms.to_vec())),
+        }
+    }
+
+    pub fn resize(&mut self, framebuffer_width: i32, framebuffer_height: i32, window_width: i32, window_height: i32) -> Result<(), Error> {
+        match self {
+            RenderBackend::Gl(gl_renderer) => gl_renderer.resize(framebuffer_width, framebuffer_height, window_width, window_height),
+            RenderBackend::Recording(_) => Ok(()),
+        }
+    }
+
+    pub fn set_graphics_settings(&mut self, graphics_settings: &GraphicsSettings) {
+        if let RenderBackend::Gl(gl_renderer) = self {
+            gl_renderer.set_graphics_settings(graphics_settings);
+        }
+    }
+
+    pub fn create_material_shader(&self, vertex_shader_source: Option<&str>, fragment_shader_source: &str) -> Result<MaterialShader, Error> {
+        match self {
+            RenderBackend::Gl(gl_renderer) => gl_renderer.create_material_shader(vertex_shader_source, fragment_shader_source),
+            RenderBackend::Recording(_) => Err(Error::Unsupported(String::from("Shaders can't be compiled in headless mode"))),
+        }
+    }
+
+    // Headless runs accept passes without compiling them, there is no GL context to do it
+    pub fn add_post_process_pass(&mut self, id: PostProcessPassId, fragment_shader_source: &str, order: i32) -> Result<(), Error> {
+        match self {
+            RenderBackend::Gl(gl_renderer) => gl_renderer.add_post_process_pass(id, fragment_shader_source, order),
+            RenderBackend::Recording(_) => Ok(()),
+        }
+    }
+
+    pub fn remove_post_process_pass(&mut self, id: PostProcessPassId) {
+        if let RenderBackend::Gl(gl_renderer) = self {
+            gl_renderer.remove_post_process_pass(id);
+        }
+    }
+
+    pub fn set_post_process_pass_enabled(&mut self, id: PostProcessPassId, enabled: bool) {
+        if let RenderBackend::Gl(gl_renderer) = self {
+            gl_renderer.set_post_process_pass_enabled(id, enabled);
+        }
+    }
+
+    pub fn set_post_process_pass_order(&mut self, id: PostProcessPassId, order: i32) {
+        if let RenderBackend::Gl(gl_renderer) = self {
+            gl_renderer.set_post_process_pass_order(id, order);
+        }
+    }
+
+    pub fn set_post_process_uniform(&mut self, id: PostProcessPassId, name: &str, value: &UniformValue) {
+        if let RenderBackend::Gl(gl_renderer) = self {
+            gl_renderer.set_post_process_uniform(id, name, value);
+        }
+    }
+
+    pub fn capture_screenshot(&self, source: ScreenshotSource) -> Result<Screenshot, Error> {
+        match self {
+            RenderBackend::Gl(gl_renderer) => gl_renderer.capture_screenshot(source),
+            RenderBackend::Recording(_) => Err(Error::Unsupported(String::from("Screenshots can't be taken in headless mode"))),
+        }
+    }
+}
+
+impl Drop for HeadlessScope {
+    fn drop(&mut self) {
+        HEADLESS.set(self.was_headless);
+    }
+}
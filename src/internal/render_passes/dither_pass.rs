@@ -0,0 +1,61 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use crate::graphics_settings::ColorDepth;
+use crate::internal::framebuffer::Framebuffer;
+use crate::internal::gl_renderer::GlRenderer;
+use crate::internal::render_passes::PassStep;
+use crate::internal::renderer_command::RendererCommand;
+use crate::internal::shader_program::ShaderProgram;
+use crate::texture::Texture;
+
+const UNIFORM_DITHER_MATRIX_SIZE_LOCATION: i32 = 0;
+
+// Reduces result of the previous pass to 15 bit color
+pub struct DitherPass;
+
+impl DitherPass {
+    pub fn new() -> DitherPass {
+        DitherPass { }
+    }
+}
+
+impl PassStep for DitherPass {
+    fn on_execute(&self, gl_renderer: &GlRenderer, framebuffer: &Framebuffer, shader: &ShaderProgram, _commands: &Vec<RendererCommand>, last_pass_result: &Option<&Texture>) {
+        let (last_pass_result, dithering) = match (last_pass_result, gl_renderer.get_graphics_settings().color_depth) {
+            (Some(last_pass_result), ColorDepth::Rgb555 { dithering }) => (last_pass_result, dithering),
+            _ => return
+        };
+
+        framebuffer.bind();
+        shader.enable();
+        gl_renderer.disable_depth_test();
+        gl_renderer.set_viewport_size(0, 0, framebuffer.get_width(), framebuffer.get_height());
+
+        shader.set_uniform_int(UNIFORM_DITHER_MATRIX_SIZE_LOCATION, dithering.get_matrix_size());
+        gl_renderer.draw_screen_quad(last_pass_result);
+
+        framebuffer.unbind();
+    }
+
+    fn is_enabled(&self, gl_renderer: &GlRenderer) -> bool {
+        gl_renderer.get_graphics_settings().color_depth != ColorDepth::Full
+    }
+}
@@ -17,60 +17,279 @@
 // along with RSFX. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use gl::types::GLuint;
+use glam::{Mat4, Vec2};
+
+use crate::internal::background_renderer::{BackgroundLayerDraw, BackgroundRenderer, SkyboxDraw};
+use crate::internal::command_sorting;
 use crate::internal::framebuffer::Framebuffer;
 use crate::internal::gl_renderer::GlRenderer;
 use crate::internal::render_passes::PassStep;
+use crate::internal::render_target_scope::RenderTargetScope;
+use crate::internal::renderable::Renderable;
 use crate::internal::renderer_command::RendererCommand;
 use crate::internal::shader_program::ShaderProgram;
+use crate::material::MAX_MATERIAL_TEXTURES;
+use crate::renderer::{BlendMode, UNIFORM_PROJECTION_MATRIX_LOCATION, UNIFORM_VIEW_MATRIX_LOCATION};
 use crate::texture::Texture;
+use crate::uniform_value::UniformValue;
+
+const UNIFORM_SNAP_GRID_LOCATION: i32 = 39;
+const UNIFORM_SNAP_HALVING_DISTANCE_LOCATION: i32 = 40;
+const UNIFORM_FRAMEBUFFER_SIZE_LOCATION: i32 = 41;
+
+pub struct MainPass {
+    // Engine uniform values carry over between frames, material shaders used for the first time
+    // still need the ones set frames ago
+    uniforms: RefCell<HashMap<i32, UniformValue>>,
+    // Last clear color of the screen, render targets set their own
+    clear_color: Cell<(f32, f32, f32)>,
+    // Skybox of the screen, same as clear color
+    skybox: Cell<Option<SkyboxDraw>>,
+    background_renderer: BackgroundRenderer,
+}
+
+// What the screen had when a render target began, it's put back when the target ends
+struct ScreenState {
+    uniforms: HashMap<i32, UniformValue>,
+    clear_color: (f32, f32, f32),
+    skybox: Option<SkyboxDraw>,
+    background_pending: bool,
+}
 
-pub struct MainPass;
+// What is currently bound, so consecutive draws don't set the same state again
+struct DrawState {
+    program_id: Option<GLuint>,
+    mesh_id: Option<GLuint>,
+    texture_ids: [Option<GLuint>; MAX_MATERIAL_TEXTURES],
+    blend_mode: BlendMode,
+    // Engine uniform values at the current point of the frame, programs get them when they draw
+    uniforms: HashMap<i32, UniformValue>,
+    // Values every program got during this pass, each program keeps its own uniforms
+    applied_uniforms: HashMap<GLuint, HashMap<i32, UniformValue>>,
+    // Material uniforms for the next draw
+    material_uniforms: Vec<(i32, UniformValue)>,
+}
+
+impl DrawState {
+    fn new(gl_renderer: &GlRenderer, uniforms: HashMap<i32, UniformValue>) -> DrawState {
+        gl_renderer.set_blend_mode(BlendMode::Opaque);
+        DrawState {
+            program_id: None,
+            mesh_id: None,
+            texture_ids: [None; MAX_MATERIAL_TEXTURES],
+            blend_mode: BlendMode::Opaque,
+            uniforms,
+            applied_uniforms: HashMap::new(),
+            material_uniforms: vec![]
+        }
+    }
+
+    fn set_blend_mode(&mut self, gl_renderer: &GlRenderer, blend_mode: BlendMode) {
+        if self.blend_mode != blend_mode {
+            self.blend_mode = blend_mode;
+            gl_renderer.set_blend_mode(blend_mode);
+        }
+    }
+
+    fn bind(&mut self, gl_renderer: &GlRenderer, built_in_shader: &ShaderProgram, renderable: &Renderable) {
+        let program_id = renderable.program_id.unwrap_or(built_in_shader.id());
+        if self.program_id != Some(program_id) {
+            ShaderProgram::use_program(program_id);
+            self.program_id = Some(program_id);
+        }
+
+        let applied_uniforms = self.applied_uniforms.entry(program_id).or_default();
+        for (location, value) in &self.uniforms {
+            // Material shaders only get engine uniforms they declare, their other uniforms may sit at the same location
+            let is_declared = renderable.program_id.is_none()
+                || (*location < 64 && renderable.engine_uniform_mask & (1 << *location) != 0);
+            if is_declared && applied_uniforms.get(location) != Some(value) {
+                ShaderProgram::set_program_uniform(program_id, *location, value);
+                applied_uniforms.insert(*location, *value);
+            }
+        }
+        for (location, value) in self.material_uniforms.drain(..) {
+            if applied_uniforms.get(&location) != Some(&value) {
+                ShaderProgram::set_program_uniform(program_id, location, &value);
+                applied_uniforms.insert(location, value);
+            }
+        }
+
+        if self.mesh_id != Some(renderable.mesh_id) {
+            gl_renderer.bind_mesh(renderable.mesh_id);
+            self.mesh_id = Some(renderable.mesh_id);
+        }
+        for (slot, texture_id) in renderable.texture_ids.iter().enumerate() {
+            if *texture_id != 0 && self.texture_ids[slot] != Some(*texture_id) {
+                gl_renderer.bind_texture(*texture_id, gl::TEXTURE0 + slot as GLuint);
+                self.texture_ids[slot] = Some(*texture_id);
+            }
+        }
+        self.set_blend_mode(gl_renderer, renderable.blend_mode);
+    }
+}
 
 impl MainPass {
-    pub fn new() -> MainPass {
-        MainPass { }
+    pub fn new(background_renderer: BackgroundRenderer) -> MainPass {
+        MainPass {
+            uniforms: RefCell::new(HashMap::new()),
+            clear_color: Cell::new((0.0, 0.0, 0.0)),
+            skybox: Cell::new(None),
+            background_renderer
+        }
+    }
+
+    // Snapping grid follows resolution of the framebuffer being drawn into
+    fn set_framebuffer_uniforms(gl_renderer: &GlRenderer, draw_state: &mut DrawState, framebuffer_size: Vec2) {
+        let (snap_grid, snap_halving_distance) = gl_renderer.get_vertex_snapping_grid(framebuffer_size);
+        draw_state.uniforms.insert(UNIFORM_SNAP_GRID_LOCATION, UniformValue::Vec2(snap_grid));
+        draw_state.uniforms.insert(UNIFORM_SNAP_HALVING_DISTANCE_LOCATION, UniformValue::Float(snap_halving_distance));
+        draw_state.uniforms.insert(UNIFORM_FRAMEBUFFER_SIZE_LOCATION, UniformValue::Vec2(framebuffer_size));
+    }
+
+    // Skybox and background layers go right after the clear, but are drawn with camera matrices the first
+    // draw after it uses, they are often set after clearing
+    fn draw_background(&self, gl_renderer: &GlRenderer, framebuffer_size: Vec2, draw_state: &mut DrawState, skybox: Option<&SkyboxDraw>, background_layers: &[&BackgroundLayerDraw]) {
+        if skybox.is_none() && background_layers.is_empty() {
+            return;
+        }
+
+        let get_matrix = |location| match draw_state.uniforms.get(&location) {
+            Some(UniformValue::Mat4(matrix)) => *matrix,
+            _ => Mat4::IDENTITY
+        };
+        let view_matrix = get_matrix(UNIFORM_VIEW_MATRIX_LOCATION);
+        let projection_matrix = get_matrix(UNIFORM_PROJECTION_MATRIX_LOCATION);
+
+        self.background_renderer.draw(gl_renderer, skybox, background_layers, &view_matrix, &projection_matrix, framebuffer_size);
+
+        // Background changed program, mesh, texture and blending behind draw state's back
+        draw_state.program_id = None;
+        draw_state.mesh_id = None;
+        draw_state.texture_ids[0] = None;
+        gl_renderer.set_blend_mode(draw_state.blend_mode);
+        gl_renderer.enable_depth_test();
     }
 }
 
 impl PassStep for MainPass {
     fn on_execute(&self, gl_renderer: &GlRenderer, framebuffer: &Framebuffer, shader: &ShaderProgram, commands: &Vec<RendererCommand>, _last_pass_result: &Option<&Texture>) {
+        let screen_size = Vec2::new(framebuffer.get_width() as f32, framebuffer.get_height() as f32);
         framebuffer.bind();
-        shader.enable();
         gl_renderer.enable_depth_test();
         gl_renderer.set_viewport_size(0, 0, framebuffer.get_width(), framebuffer.get_height());
 
+        let mut draw_state = DrawState::new(gl_renderer, self.uniforms.take());
+        MainPass::set_framebuffer_uniforms(gl_renderer, &mut draw_state, screen_size);
+
+        // Background layers of the screen first, then of every render target in the order they begin
+        let mut background_layers: Vec<Vec<&BackgroundLayerDraw>> = vec![vec![]];
+        let mut layer_scope = 0;
         for command in commands {
+            match command {
+                RendererCommand::BeginRenderTarget(_) => {
+                    background_layers.push(vec![]);
+                    layer_scope = background_layers.len() - 1;
+                }
+                RendererCommand::EndRenderTarget() => layer_scope = 0,
+                RendererCommand::DrawBackgroundLayer(layer) => background_layers[layer_scope].push(layer),
+                _ => {}
+            }
+        }
+
+        let mut layer_scope = 0;
+        let mut render_target_count = 0;
+        let mut framebuffer_size = screen_size;
+        let mut background_pending = false;
+        let mut clear_color = self.clear_color.get();
+        let mut skybox = self.skybox.get();
+        let mut screen_state: RenderTargetScope<ScreenState> = RenderTargetScope::new();
+
+        for command in &command_sorting::sort_commands(commands) {
+            if background_pending && command.get_renderable().is_some() {
+                self.draw_background(gl_renderer, framebuffer_size, &mut draw_state, skybox.as_ref(), &background_layers[layer_scope]);
+                background_pending = false;
+            }
+
             match command {
                 RendererCommand::ClearScreen() => {
+                    // Depth buffer is not cleared while depth writes are off
+                    draw_state.set_blend_mode(gl_renderer, BlendMode::Opaque);
                     gl_renderer.clear_buffer();
+                    background_pending = true;
                 }
                 RendererCommand::SetClearColor(r, g, b) => {
+                    clear_color = (*r, *g, *b);
                     gl_renderer.set_clear_color(*r, *g, *b);
                 }
                 RendererCommand::Render(renderable) => {
-                    gl_renderer.bind_mesh(renderable.mesh_id);
-                    gl_renderer.bind_texture(renderable.texture_id, gl::TEXTURE0);
-                    gl_renderer.draw_arrays(renderable.vertices_count);
-                    gl_renderer.unbind_mesh();
+                    draw_state.bind(gl_renderer, shader, renderable);
+                    gl_renderer.draw(renderable.vertices_count, renderable.index_type);
                 }
-                RendererCommand::SetUniformInt(location, value) => {
-                    shader.set_uniform_int(*location, *value);
+                RendererCommand::RenderInstanced(renderable, first_instance, instance_count) => {
+                    draw_state.bind(gl_renderer, shader, renderable);
+                    gl_renderer.draw_instanced(renderable.vertices_count, renderable.index_type, *first_instance, *instance_count as i32);
                 }
-                RendererCommand::SetUniformFloat(location, value) => {
-                    shader.set_uniform_float(*location, *value);
+                RendererCommand::RenderParticles(renderable, first_vertex) => {
+                    let renderable = Renderable { mesh_id: gl_renderer.get_particle_mesh_id(), ..*renderable };
+                    draw_state.bind(gl_renderer, shader, &renderable);
+                    gl_renderer.draw_arrays_range(*first_vertex, renderable.vertices_count);
                 }
-                RendererCommand::SetUniformVec3(location, value) => {
-                    shader.set_uniform_vec3(*location, value);
+                RendererCommand::SetSkybox(new_skybox) => skybox = *new_skybox,
+                RendererCommand::SetMaterialUniform(location, value) => {
+                    draw_state.material_uniforms.push((*location, *value));
                 }
-                RendererCommand::SetUniformVec4(location, value) => {
-                    shader.set_uniform_vec4(*location, value);
+                RendererCommand::BeginRenderTarget(render_target) => {
+                    screen_state.begin(|| ScreenState { uniforms: draw_state.uniforms.clone(), clear_color, skybox, background_pending });
+                    render_target_count += 1;
+                    layer_scope = render_target_count;
+                    background_pending = false;
+
+                    render_target.bind();
+                    gl_renderer.set_viewport_size(0, 0, render_target.width, render_target.height);
+                    framebuffer_size = Vec2::new(render_target.width as f32, render_target.height as f32);
+                    MainPass::set_framebuffer_uniforms(gl_renderer, &mut draw_state, framebuffer_size);
                 }
-                RendererCommand::SetUniformMat4(location, value) => {
-                    shader.set_uniform_mat4(*location, value);
+                RendererCommand::EndRenderTarget() => {
+                    if background_pending {
+                        self.draw_background(gl_renderer, framebuffer_size, &mut draw_state, skybox.as_ref(), &background_layers[layer_scope]);
+                    }
+                    if let Some(screen_state) = screen_state.end() {
+                        draw_state.uniforms = screen_state.uniforms;
+                        clear_color = screen_state.clear_color;
+                        skybox = screen_state.skybox;
+                        gl_renderer.set_clear_color(clear_color.0, clear_color.1, clear_color.2);
+                        background_pending = screen_state.background_pending;
+                    }
+                    layer_scope = 0;
+
+                    framebuffer.bind();
+                    gl_renderer.set_viewport_size(0, 0, framebuffer.get_width(), framebuffer.get_height());
+                    framebuffer_size = screen_size;
+                }
+                _ => {
+                    if let Some((location, value)) = command.get_uniform() {
+                        draw_state.uniforms.insert(location, value);
+                    }
                 }
             }
         }
 
+        // Frame with nothing drawn after the clear still shows the background
+        if background_pending {
+            self.draw_background(gl_renderer, framebuffer_size, &mut draw_state, skybox.as_ref(), &background_layers[layer_scope]);
+        }
+
+        self.uniforms.replace(draw_state.uniforms);
+        self.clear_color.set(clear_color);
+        self.skybox.set(skybox);
+
+        gl_renderer.set_blend_mode(BlendMode::Opaque);
+        gl_renderer.unbind_mesh();
         framebuffer.unbind();
     }
-}
\ No newline at end of file
+}
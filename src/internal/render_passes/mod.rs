@@ -18,7 +18,11 @@
 //
 
 pub(crate) mod main_pass;
+pub(crate) mod overlay_pass;
+pub(crate) mod dither_pass;
+pub(crate) mod post_process_pass;
 
+use crate::error::Error;
 use crate::internal::framebuffer::Framebuffer;
 use crate::internal::gl_renderer::GlRenderer;
 use crate::internal::renderer_command::RendererCommand;
@@ -27,6 +31,11 @@ use crate::texture::Texture;
 
 pub trait PassStep {
     fn on_execute(&self, gl_renderer: &GlRenderer, framebuffer: &Framebuffer, shader: &ShaderProgram, commands: &Vec<RendererCommand>, last_pass_result: &Option<&Texture>);
+
+    // Disabled passes are skipped, the next pass gets result of the last one that ran
+    fn is_enabled(&self, _gl_renderer: &GlRenderer) -> bool {
+        true
+    }
 }
 
 pub struct RenderPass {
@@ -44,8 +53,25 @@ impl RenderPass {
         self.pass_step.on_execute(gl_renderer, &self.framebuffer, &self.shader, commands, last_pass_result);
     }
 
+    pub fn is_enabled(&self, gl_renderer: &GlRenderer) -> bool {
+        self.pass_step.is_enabled(gl_renderer)
+    }
+
+    pub fn resize(&mut self, width: i32, height: i32) -> Result<(), Error> {
+        self.framebuffer = Framebuffer::new(width, height)?;
+        Ok(())
+    }
+
+    pub fn get_shader(&self) -> &ShaderProgram {
+        &self.shader
+    }
+
+    pub fn get_framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
     pub fn get_pass_result(&self) -> &Texture {
-        &self.framebuffer.get_texture()
+        self.framebuffer.get_texture()
     }
 }
 
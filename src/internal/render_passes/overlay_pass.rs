@@ -0,0 +1,121 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use std::cell::RefCell;
+
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::internal::framebuffer::Framebuffer;
+use crate::internal::gl_renderer::GlRenderer;
+use crate::internal::overlay_sprite::OverlaySprite;
+use crate::internal::render_passes::PassStep;
+use crate::internal::renderer_command::RendererCommand;
+use crate::internal::shader_program::ShaderProgram;
+use crate::mesh::Mesh;
+use crate::renderer::BlendMode;
+use crate::texture::{ImageMode, Texture};
+use crate::vertex_data::VertexData;
+
+const UNIFORM_FRAMEBUFFER_SIZE_LOCATION: i32 = 0;
+
+// Draws screen space sprites over result of the previous pass. There is no depth, fog, lighting or
+// snapping here, sprites are drawn by layer and then in the order they were submitted.
+pub struct OverlayPass {
+    // Vertices of every sprite in the frame, rebuilt each time the pass runs
+    sprite_mesh: RefCell<Mesh>,
+    // Bound for untextured quads
+    white_texture: Texture,
+}
+
+impl OverlayPass {
+    pub fn new() -> OverlayPass {
+        OverlayPass {
+            sprite_mesh: RefCell::new(Mesh::from_raw_data(&vec![])),
+            white_texture: Texture::from_data(&vec![255, 255, 255, 255], 1, 1, ImageMode::RGBA)
+        }
+    }
+}
+
+impl PassStep for OverlayPass {
+    fn on_execute(&self, gl_renderer: &GlRenderer, framebuffer: &Framebuffer, shader: &ShaderProgram, commands: &Vec<RendererCommand>, last_pass_result: &Option<&Texture>) {
+        let last_pass_result = match last_pass_result {
+            Some(last_pass_result) => last_pass_result,
+            None => return
+        };
+
+        framebuffer.bind();
+        gl_renderer.disable_depth_test();
+        gl_renderer.set_viewport_size(0, 0, framebuffer.get_width(), framebuffer.get_height());
+        gl_renderer.draw_screen_texture(last_pass_result);
+
+        let mut sprites: Vec<&OverlaySprite> = commands.iter()
+            .filter_map(|command| match command {
+                RendererCommand::DrawSprite(sprite) => Some(sprite),
+                _ => None
+            })
+            .collect();
+        if sprites.is_empty() {
+            framebuffer.unbind();
+            return;
+        }
+        // Stable, sprites of the same layer keep their submission order
+        sprites.sort_by_key(|sprite| sprite.layer);
+
+        let mut vertex_data: Vec<VertexData> = Vec::with_capacity(sprites.len() * 6);
+        let mut colors: Vec<Vec4> = Vec::with_capacity(sprites.len() * 6);
+        for sprite in &sprites {
+            let corners = sprite.get_corners();
+            for index in [0, 1, 2, 0, 2, 3] {
+                let (position, texture_coords) = corners[index];
+                vertex_data.push(VertexData::new(position.extend(0.0), texture_coords, Vec3::ZERO));
+                colors.push(sprite.tint);
+            }
+        }
+
+        let mut sprite_mesh = self.sprite_mesh.borrow_mut();
+        sprite_mesh.update_vertex_data(&vertex_data, &colors);
+
+        shader.enable();
+        shader.set_uniform_vec2(UNIFORM_FRAMEBUFFER_SIZE_LOCATION, &Vec2::new(framebuffer.get_width() as f32, framebuffer.get_height() as f32));
+        gl_renderer.set_blend_mode(BlendMode::Alpha);
+        // Sprites with negative size are mirrored, they must not get culled
+        gl_renderer.set_face_culling(false);
+        gl_renderer.bind_mesh(sprite_mesh.vao_id());
+
+        // Neighbouring sprites with the same texture are drawn together
+        let mut batch_start = 0;
+        while batch_start < sprites.len() {
+            let texture_id = sprites[batch_start].texture_id;
+            let batch_end = sprites[batch_start..].iter()
+                .position(|sprite| sprite.texture_id != texture_id)
+                .map_or(sprites.len(), |offset| batch_start + offset);
+
+            let texture_id = if texture_id == 0 { self.white_texture.texture_id() } else { texture_id };
+            gl_renderer.bind_texture(texture_id, gl::TEXTURE0);
+            gl_renderer.draw_arrays_range(batch_start as i32 * 6, (batch_end - batch_start) as i32 * 6);
+            batch_start = batch_end;
+        }
+
+        gl_renderer.unbind_mesh();
+        gl_renderer.set_face_culling(true);
+        gl_renderer.set_blend_mode(BlendMode::Opaque);
+        framebuffer.unbind();
+    }
+}
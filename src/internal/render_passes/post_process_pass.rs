@@ -0,0 +1,72 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use glam::Vec2;
+
+use crate::internal::framebuffer::Framebuffer;
+use crate::internal::gl_renderer::GlRenderer;
+use crate::internal::render_passes::{PassStep, RenderPass};
+use crate::internal::renderer_command::RendererCommand;
+use crate::internal::shader_program::ShaderProgram;
+use crate::post_process::PostProcessPassId;
+use crate::texture::Texture;
+
+const UNIFORM_FRAMEBUFFER_SIZE_NAME: &str = "framebuffer_size";
+
+// Draws result of the previous pass through a user fragment shader
+pub struct PostProcessPass {
+    // -1 when shader doesn't use it
+    framebuffer_size_location: i32,
+}
+
+// Registered pass with the state that can be changed at runtime
+pub struct PostProcessPassSlot {
+    pub id: PostProcessPassId,
+    pub order: i32,
+    pub enabled: bool,
+    pub render_pass: RenderPass,
+}
+
+impl PostProcessPass {
+    pub fn new(shader: &ShaderProgram) -> PostProcessPass {
+        PostProcessPass { framebuffer_size_location: shader.get_uniform_location(UNIFORM_FRAMEBUFFER_SIZE_NAME) }
+    }
+}
+
+impl PassStep for PostProcessPass {
+    fn on_execute(&self, gl_renderer: &GlRenderer, framebuffer: &Framebuffer, shader: &ShaderProgram, _commands: &Vec<RendererCommand>, last_pass_result: &Option<&Texture>) {
+        let last_pass_result = match last_pass_result {
+            Some(last_pass_result) => last_pass_result,
+            None => return
+        };
+
+        framebuffer.bind();
+        shader.enable();
+        gl_renderer.disable_depth_test();
+        gl_renderer.set_viewport_size(0, 0, framebuffer.get_width(), framebuffer.get_height());
+
+        if self.framebuffer_size_location >= 0 {
+            shader.set_uniform_vec2(self.framebuffer_size_location, &Vec2::new(framebuffer.get_width() as f32, framebuffer.get_height() as f32));
+        }
+        gl_renderer.draw_screen_quad(last_pass_result);
+
+        framebuffer.unbind();
+    }
+}
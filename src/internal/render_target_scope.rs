@@ -0,0 +1,67 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+// What the screen had when a render target began, handed back when the target ends. Renderer, command sorting
+// and main pass each keep their own part of the screen state in one. Targets don't nest, a begin that
+// comes while a target is open keeps what the first one saved.
+pub struct RenderTargetScope<T> {
+    screen: Option<T>,
+}
+
+impl<T> RenderTargetScope<T> {
+    pub fn new() -> RenderTargetScope<T> {
+        RenderTargetScope { screen: None }
+    }
+
+    // save is only called when the screen is not saved yet
+    pub fn begin<F>(&mut self, save: F)
+        where F: FnOnce() -> T
+    {
+        if self.screen.is_none() {
+            self.screen = Some(save());
+        }
+    }
+
+    // State to put back, None when no target was open
+    pub fn end(&mut self) -> Option<T> {
+        self.screen.take()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.screen.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_saved_by_the_first_begin_is_handed_back() {
+        let mut scope = RenderTargetScope::new();
+        scope.begin(|| 1);
+        scope.begin(|| 2);
+
+        assert!(scope.is_open());
+        assert_eq!(scope.end(), Some(1));
+        assert!(!scope.is_open());
+        assert_eq!(scope.end(), None);
+    }
+}
@@ -17,19 +17,49 @@
 // along with RSFX. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use gl::types::{GLsizei, GLuint};
+use gl::types::{GLenum, GLsizei, GLuint};
+use crate::material::{Material, MAX_MATERIAL_TEXTURES};
 use crate::mesh::Mesh;
-use crate::texture::Texture;
+use crate::renderer::BlendMode;
 
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub struct Renderable {
     pub mesh_id: GLuint,
     pub vertices_count: GLsizei,
-    pub texture_id: GLuint,
+    // Set for meshes drawn through an element buffer
+    pub index_type: Option<GLenum>,
+    // Built-in main pass shader when not set
+    pub program_id: Option<GLuint>,
+    // Engine uniform locations the program declares, see MaterialShader
+    pub engine_uniform_mask: u64,
+    // Texture per binding slot, 0 for empty slots
+    pub texture_ids: [GLuint; MAX_MATERIAL_TEXTURES],
+    pub blend_mode: BlendMode,
 }
 
 impl Renderable {
-    pub fn new(mesh: &Mesh, texture: &Texture) -> Renderable {
-        Renderable { mesh_id: mesh.vao_id(), vertices_count: mesh.vertices_count(), texture_id: texture.texture_id() }
+    pub fn new(mesh: &Mesh, material: &Material) -> Renderable {
+        Renderable {
+            mesh_id: mesh.vao_id(),
+            vertices_count: mesh.vertices_count(),
+            index_type: mesh.index_type(),
+            program_id: material.get_program_id(),
+            engine_uniform_mask: material.get_engine_uniform_mask(),
+            texture_ids: material.get_texture_ids(),
+            blend_mode: material.get_blend_mode()
+        }
     }
-}
\ No newline at end of file
+
+    // Geometry the pass provides itself, like the frame's particles
+    pub fn without_mesh(material: &Material, vertices_count: GLsizei) -> Renderable {
+        Renderable {
+            mesh_id: 0,
+            vertices_count,
+            index_type: None,
+            program_id: material.get_program_id(),
+            engine_uniform_mask: material.get_engine_uniform_mask(),
+            texture_ids: material.get_texture_ids(),
+            blend_mode: material.get_blend_mode()
+        }
+    }
+}
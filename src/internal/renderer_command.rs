@@ -18,16 +18,64 @@
 //
 
 use glam::{Mat4, Vec3, Vec4};
+use crate::internal::background_renderer::{BackgroundLayerDraw, SkyboxDraw};
+use crate::internal::framebuffer::FramebufferBinding;
+use crate::internal::overlay_sprite::OverlaySprite;
 use crate::internal::renderable::Renderable;
+use crate::uniform_value::UniformValue;
 
 #[derive(Copy, Clone, PartialEq)]
 pub enum RendererCommand {
     ClearScreen(),
     SetClearColor(f32, f32, f32),
     Render(Renderable),
+    // Draws the renderable once per instance transform, taking count transforms from the frame's instance
+    // transforms starting at the given index
+    RenderInstanced(Renderable, u32, u32),
+    // Draws vertices count of the renderable from the frame's particle vertices, starting at the given vertex
+    RenderParticles(Renderable, i32),
     SetUniformInt(i32, i32),
     SetUniformFloat(i32, f32),
     SetUniformVec3(i32, Vec3),
     SetUniformVec4(i32, Vec4),
     SetUniformMat4(i32, Mat4),
+    // Uniform of the material used by the next draw, only that draw sees it
+    SetMaterialUniform(i32, UniformValue),
+    // Screen space sprite, drawn by the overlay pass after the 3D scene
+    DrawSprite(OverlaySprite),
+    // Drawn behind the 3D scene after every clear, over the skybox
+    DrawBackgroundLayer(BackgroundLayerDraw),
+    // Drawn after every clear from now on, stays for the next frames
+    SetSkybox(Option<SkyboxDraw>),
+    // Commands until EndRenderTarget draw into the render target, uniforms, clear color and skybox set in
+    // between don't apply to the screen. Renderer keeps the framebuffer alive until the passes ran.
+    BeginRenderTarget(FramebufferBinding),
+    EndRenderTarget(),
+}
+
+impl RendererCommand {
+    // Engine uniforms only, they stay set for every draw after them
+    pub fn uniform_location(&self) -> Option<i32> {
+        self.get_uniform().map(|(location, _)| location)
+    }
+
+    pub fn get_uniform(&self) -> Option<(i32, UniformValue)> {
+        match self {
+            RendererCommand::SetUniformInt(location, value) => Some((*location, UniformValue::Int(*value))),
+            RendererCommand::SetUniformFloat(location, value) => Some((*location, UniformValue::Float(*value))),
+            RendererCommand::SetUniformVec3(location, value) => Some((*location, UniformValue::Vec3(*value))),
+            RendererCommand::SetUniformVec4(location, value) => Some((*location, UniformValue::Vec4(*value))),
+            RendererCommand::SetUniformMat4(location, value) => Some((*location, UniformValue::Mat4(*value))),
+            _ => None
+        }
+    }
+
+    pub fn get_renderable(&self) -> Option<&Renderable> {
+        match self {
+            RendererCommand::Render(renderable) => Some(renderable),
+            RendererCommand::RenderInstanced(renderable, _, _) => Some(renderable),
+            RendererCommand::RenderParticles(renderable, _) => Some(renderable),
+            _ => None
+        }
+    }
 }
\ No newline at end of file
@@ -18,29 +18,34 @@
 //
 
 use gl;
-use glam::{Mat4, Vec3, Vec4};
+use glam::{Mat4, Vec2, Vec3, Vec4};
 use std;
+use std::collections::HashMap;
 use std::ffi::{CStr, CString};
 
+use crate::error::{Error, ShaderStage};
+use crate::uniform_value::UniformValue;
+
 pub struct ShaderProgram {
     id: gl::types::GLuint,
+    // Active uniforms by location, array elements are listed one by one as name[index]
+    uniform_names: HashMap<i32, String>,
 }
 
 impl ShaderProgram {
-    pub fn load_shaders(vert_shader_source: &CStr, frag_vert_source: &CStr) -> ShaderProgram {
-        let vert_shader = match Shader::from_vert_source(vert_shader_source) {
-            Ok(shader) => shader,
-            Err(error) => panic!("Failed to compile vertex shader: {:?}", error)
-        };
-        let frag_shader = match Shader::from_frag_source(frag_vert_source) {
-            Ok(shader) => shader,
-            Err(error) => panic!("Failed to compile fragment shader: {:?}", error)
-        };
+    pub fn load_shaders(vert_shader_source: &CStr, frag_vert_source: &CStr) -> Result<ShaderProgram, Error> {
+        let vert_shader = Shader::from_vert_source(vert_shader_source)?;
+        let frag_shader = Shader::from_frag_source(frag_vert_source)?;
 
-        match ShaderProgram::from_shaders(&[vert_shader, frag_shader]) {
-            Ok(shader_program) => shader_program,
-            Err(error) => panic!("Failed to compile shader program: {:?}", error)
-        }
+        ShaderProgram::from_shaders(&[vert_shader, frag_shader])
+    }
+
+    pub fn load_shaders_with_geometry(vert_shader_source: &CStr, geom_shader_source: &CStr, frag_vert_source: &CStr) -> Result<ShaderProgram, Error> {
+        let vert_shader = Shader::from_vert_source(vert_shader_source)?;
+        let geom_shader = Shader::from_geom_source(geom_shader_source)?;
+        let frag_shader = Shader::from_frag_source(frag_vert_source)?;
+
+        ShaderProgram::from_shaders(&[vert_shader, geom_shader, frag_shader])
     }
 
     pub fn id(&self) -> gl::types::GLuint {
@@ -59,6 +64,49 @@ impl ShaderProgram {
         }
     }
 
+    pub fn get_uniform_name(&self, location: i32) -> Option<&str> {
+        self.uniform_names.get(&location).map(|name| name.as_str())
+    }
+
+    // -1 when there is no active uniform with given name
+    pub fn get_uniform_location(&self, name: &str) -> i32 {
+        match CString::new(name) {
+            Ok(name) => unsafe { gl::GetUniformLocation(self.id, name.as_ptr()) },
+            Err(_) => -1
+        }
+    }
+
+    pub fn use_program(program_id: gl::types::GLuint) {
+        unsafe {
+            gl::UseProgram(program_id);
+        }
+    }
+
+    // Sets uniform of any program, whether it's in use or not
+    pub fn set_program_uniform(program_id: gl::types::GLuint, location: i32, value: &UniformValue) {
+        unsafe {
+            match value {
+                UniformValue::Int(value) => gl::ProgramUniform1i(program_id, location, *value),
+                UniformValue::Float(value) => gl::ProgramUniform1f(program_id, location, *value),
+                UniformValue::Vec2(value) => gl::ProgramUniform2f(program_id, location, value.x, value.y),
+                UniformValue::Vec3(value) => gl::ProgramUniform3f(program_id, location, value.x, value.y, value.z),
+                UniformValue::Vec4(value) => gl::ProgramUniform4f(program_id, location, value.x, value.y, value.z, value.w),
+                UniformValue::Mat4(value) => gl::ProgramUniformMatrix4fv(program_id, location, 1, gl::FALSE, value.as_ref() as _),
+            }
+        }
+    }
+
+    pub fn set_uniform(&self, location: i32, value: &UniformValue) {
+        match value {
+            UniformValue::Int(value) => self.set_uniform_int(location, *value),
+            UniformValue::Float(value) => self.set_uniform_float(location, *value),
+            UniformValue::Vec2(value) => self.set_uniform_vec2(location, value),
+            UniformValue::Vec3(value) => self.set_uniform_vec3(location, value),
+            UniformValue::Vec4(value) => self.set_uniform_vec4(location, value),
+            UniformValue::Mat4(value) => self.set_uniform_mat4(location, value),
+        }
+    }
+
     pub fn set_uniform_int(&self, location: i32, value: i32) {
         unsafe {
             gl::Uniform1i(location, value);
@@ -71,6 +119,12 @@ impl ShaderProgram {
         }
     }
     
+    pub fn set_uniform_vec2(&self, location: i32, value: &Vec2) {
+        unsafe {
+            gl::Uniform2f(location, value[0], value[1]);
+        }
+    }
+    
     pub fn set_uniform_vec3(&self, location: i32, value: &Vec3) {
         unsafe {
             gl::Uniform3f(location, value[0], value[1], value[2]);
@@ -89,7 +143,7 @@ impl ShaderProgram {
         }
     }
 
-    fn from_shaders(shaders: &[Shader]) -> Result<ShaderProgram, String> {
+    fn from_shaders(shaders: &[Shader]) -> Result<ShaderProgram, Error> {
         let program_id = unsafe { gl::CreateProgram() };
 
         for shader in shaders {
@@ -124,7 +178,11 @@ impl ShaderProgram {
                 );
             }
 
-            return Err(error.to_string_lossy().into_owned());
+            unsafe {
+                gl::DeleteProgram(program_id);
+            }
+
+            return Err(Error::ShaderLink { info_log: error.to_string_lossy().into_owned() });
         }
 
         unsafe {
@@ -153,7 +211,11 @@ impl ShaderProgram {
                 );
             }
 
-            return Err(error.to_string_lossy().into_owned());
+            unsafe {
+                gl::DeleteProgram(program_id);
+            }
+
+            return Err(Error::ShaderLink { info_log: error.to_string_lossy().into_owned() });
         }
 
 
@@ -163,7 +225,8 @@ impl ShaderProgram {
             }
         }
 
-        Ok(ShaderProgram { id: program_id })
+        let uniform_names = read_active_uniforms(program_id);
+        Ok(ShaderProgram { id: program_id, uniform_names })
     }
 }
 
@@ -180,12 +243,16 @@ struct Shader {
 }
 
 impl Shader {
-    pub fn from_vert_source(source: &CStr) -> Result<Shader, String> {
-        Shader::from_source(source, gl::VERTEX_SHADER)
+    pub fn from_vert_source(source: &CStr) -> Result<Shader, Error> {
+        Shader::from_source(source, gl::VERTEX_SHADER).map_err(|info_log| Error::ShaderCompile { stage: ShaderStage::Vertex, info_log })
+    }
+
+    pub fn from_geom_source(source: &CStr) -> Result<Shader, Error> {
+        Shader::from_source(source, gl::GEOMETRY_SHADER).map_err(|info_log| Error::ShaderCompile { stage: ShaderStage::Geometry, info_log })
     }
 
-    pub fn from_frag_source(source: &CStr) -> Result<Shader, String> {
-        Shader::from_source(source, gl::FRAGMENT_SHADER)
+    pub fn from_frag_source(source: &CStr) -> Result<Shader, Error> {
+        Shader::from_source(source, gl::FRAGMENT_SHADER).map_err(|info_log| Error::ShaderCompile { stage: ShaderStage::Fragment, info_log })
     }
 
     pub fn id(&self) -> gl::types::GLuint {
@@ -226,6 +293,10 @@ impl Shader {
                 );
             }
 
+            unsafe {
+                gl::DeleteShader(id);
+            }
+
             return Err(error.to_string_lossy().into_owned());
         }
 
@@ -241,6 +312,65 @@ impl Drop for Shader {
     }
 }
 
+fn read_active_uniforms(program_id: gl::types::GLuint) -> HashMap<i32, String> {
+    let mut uniform_names = HashMap::new();
+
+    let mut uniforms_count: gl::types::GLint = 0;
+    unsafe {
+        gl::GetProgramInterfaceiv(program_id, gl::UNIFORM, gl::ACTIVE_RESOURCES, &mut uniforms_count);
+    }
+
+    let properties = [gl::NAME_LENGTH, gl::LOCATION, gl::ARRAY_SIZE];
+    for index in 0..uniforms_count as gl::types::GLuint {
+        let mut values: [gl::types::GLint; 3] = [0; 3];
+        unsafe {
+            gl::GetProgramResourceiv(
+                program_id,
+                gl::UNIFORM,
+                index,
+                properties.len() as gl::types::GLsizei,
+                properties.as_ptr(),
+                values.len() as gl::types::GLsizei,
+                std::ptr::null_mut(),
+                values.as_mut_ptr()
+            );
+        }
+        let [name_length, location, array_size] = values;
+        // Uniforms in blocks don't have a location
+        if location < 0 || name_length <= 0 {
+            continue;
+        }
+
+        let mut name_buffer: Vec<u8> = vec![0; name_length as usize];
+        unsafe {
+            gl::GetProgramResourceName(
+                program_id,
+                gl::UNIFORM,
+                index,
+                name_length,
+                std::ptr::null_mut(),
+                name_buffer.as_mut_ptr() as *mut gl::types::GLchar
+            );
+        }
+        // Name length includes the nul terminator
+        let name = String::from_utf8_lossy(&name_buffer[..name_length as usize - 1]).into_owned();
+
+        // Arrays are reported once, by their first element
+        match name.strip_suffix("[0]") {
+            Some(array_name) => {
+                for element in 0..array_size {
+                    uniform_names.insert(location + element, format!("{}[{}]", array_name, element));
+                }
+            }
+            None => {
+                uniform_names.insert(location, name);
+            }
+        }
+    }
+
+    uniform_names
+}
+
 fn allocate_buffer_for_gl_message(len: usize) -> CString {
     // allocate buffer of correct size
     let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
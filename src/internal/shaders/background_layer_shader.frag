@@ -0,0 +1,39 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+#version 450 core
+
+in vec2 frag_texture_coords;
+
+out vec4 color;
+
+layout(location = 0) uniform vec2 framebuffer_size;
+layout(location = 1) uniform vec2 texture_size;
+// In framebuffer pixels
+layout(location = 2) uniform vec2 offset;
+layout(location = 3) uniform vec4 tint;
+
+layout(binding = 0) uniform sampler2D texture_sampler;
+
+void main(void) {
+    // One texture pixel per framebuffer pixel, texture centered on the screen and repeating
+    vec2 pixel = (frag_texture_coords - 0.5) * framebuffer_size - offset;
+    color = texture2D(texture_sampler, pixel / texture_size + 0.5) * tint;
+}
\ No newline at end of file
@@ -0,0 +1,60 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+#version 450 core
+
+in vec2 frag_texture_coords;
+
+out vec4 color;
+
+layout(binding = 0) uniform sampler2D texture_sampler;
+
+// Size of Bayer matrix, 0 turns dithering off
+layout(location = 0) uniform int dither_matrix_size;
+
+const int bayer_2x2[2][2] = {
+    {0, 2},
+    {3, 1}
+};
+
+// https://en.wikipedia.org/wiki/Ordered_dithering
+// Larger matrices are built from the 2x2 one, each level adds 4 times finer thresholds
+float get_threshold(ivec2 pixel, int matrix_size) {
+    int value = 0;
+    int multiplier = 1;
+    for (int step = matrix_size / 2; step >= 1; step /= 2) {
+        ivec2 quadrant = (pixel / step) % 2;
+        value += multiplier * bayer_2x2[quadrant.y][quadrant.x];
+        multiplier *= 4;
+    }
+    return (float(value) + 0.5) / float(matrix_size * matrix_size);
+}
+
+void main(void) {
+    vec4 sampled_color = texture2D(texture_sampler, frag_texture_coords);
+
+    // 31 levels per channel, plain rounding without dithering
+    float threshold = 0.5;
+    if (dither_matrix_size > 0) {
+        threshold = get_threshold(ivec2(gl_FragCoord.xy) % dither_matrix_size, dither_matrix_size);
+    }
+    vec3 quantized = floor(sampled_color.rgb * 31.0 + threshold) / 31.0;
+
+    color = vec4(clamp(quantized, 0.0, 1.0), sampled_color.a);
+}
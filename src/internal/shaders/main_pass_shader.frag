@@ -20,8 +20,11 @@
 #version 450 core
 
 in vec2 frag_texture_coords;
+noperspective in vec2 frag_affine_texture_coords;
 in vec3 frag_normal;
-in float frag_fog_density;
+in float frag_fog_factor;
+in vec3 frag_light_color;
+in vec4 frag_vertex_color;
 
 out vec4 color;
 
@@ -31,49 +34,20 @@ layout(location = 17) uniform vec3 directional_light_color;
 layout(location = 18) uniform vec3 directional_light_direction;
 layout(location = 19) uniform float directional_light_brightness;
 
-// https://en.wikipedia.org/wiki/Ordered_dithering
-const int threshold_map[8][8] = {
-    { 0, 32,  8, 40,  2, 34, 10, 42},
-    {48, 16, 56, 24, 50, 18, 58, 26}, 
-    {12, 44,  4, 36, 14, 46,  6, 38}, 
-    {60, 28, 52, 20, 62, 30, 54, 22}, 
-    { 3, 35, 11, 43,  1, 33,  9, 41}, 
-    {51, 19, 59, 27, 49, 17, 57, 25},
-    {15, 47,  7, 39, 13, 45,  5, 37},
-    {63, 31, 55, 23, 61, 29, 53, 21}
-};
+layout(location = 42) uniform vec3 fog_color;
 
-float number_of_colors = 255.0;
-float dither_scale = 1.0;
-
-float get_threshold(int x, int y, float brightness) {
-    float limit = (threshold_map[x][y] + 1.0) / 64.0;
-    
-    return brightness < limit ? 0.0 : 1.0; 
-}
-
-float luma(vec3 color) {
-    return dot(color, vec3(0.299, 0.587, 0.114));
-}
+// 0 perspective correct, 1 affine
+layout(location = 37) uniform int texture_mapping;
 
 void main(void) {
-//    vec4 sampled_color = texture2D(texture_sampler, frag_texture_coords);
-//    
-//    vec2 xy = gl_FragCoord.xy * dither_scale;
-//    int x = int(mod(xy.x, 8));
-//    int y = int(mod(xy.y, 8));
-//    
-//    //vec3 dithered_color = floor(sampled_color.rgb * number_of_colors + 0.5) / number_of_colors;
-//    
-//    float threshold = get_threshold(x, y, luma(sampled_color.rgb));
-//    vec3 dithered_color = sampled_color.rgb * threshold;
-//
-//    color = vec4(dithered_color, sampled_color.a);
-
     vec3 unit_normal = normalize(frag_normal);
     float normal_light_dot_product = dot(unit_normal, directional_light_direction);
     float brightness = max(normal_light_dot_product, directional_light_brightness);
-    vec3 diffuse = vec3(brightness * directional_light_color);
+    vec3 diffuse = vec3(brightness * directional_light_color) + frag_light_color;
+
+    vec2 texture_coords = texture_mapping == 1 ? frag_affine_texture_coords : frag_texture_coords;
 
-    color = vec4(diffuse, 1.0) * texture2D(texture_sampler, frag_texture_coords) * frag_fog_density;
+    // Baked vertex lighting and tint modulate texture the same way
+    vec4 lit_color = vec4(diffuse, 1.0) * frag_vertex_color * texture2D(texture_sampler, texture_coords);
+    color = vec4(mix(lit_color.rgb, fog_color, frag_fog_factor), lit_color.a);
 }
\ No newline at end of file
@@ -22,6 +22,9 @@
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec2 texture_coords;
 layout(location = 2) in vec3 normal;
+layout(location = 3) in vec4 vertex_color;
+// Identity unless the mesh is drawn instanced
+layout(location = 4) in mat4 instance_transformation_matrix;
 
 layout(location = 3)  uniform mat4 transformation_matrix;
 layout(location = 7)  uniform mat4 projection_matrix;
@@ -29,12 +32,34 @@ layout(location = 11) uniform mat4 view_matrix;
 
 layout(location = 15) uniform float fog_min;
 layout(location = 16) uniform float fog_max;
+// 0 linear, 1 exponential, 2 exponential squared, 3 off
+layout(location = 43) uniform int fog_mode;
+layout(location = 44) uniform float fog_density;
+// Height fog is full below top - thickness and gone above top, zero thickness turns it off
+layout(location = 45) uniform float fog_height_top;
+layout(location = 46) uniform float fog_height_thickness;
 
-out vec2 frag_texture_coords;
-out vec3 frag_normal;
-out float frag_fog_density;
+// Nearest point and spot lights picked by the engine, in world space
+const int MAX_LIGHTS = 4;
+layout(location = 20) uniform int light_count;
+layout(location = 21) uniform vec3 light_positions[MAX_LIGHTS];
+layout(location = 25) uniform vec3 light_colors[MAX_LIGHTS];
+layout(location = 29) uniform vec3 light_directions[MAX_LIGHTS];
+// Range, falloff, cosine of outer and inner spot angle
+layout(location = 33) uniform vec4 light_parameters[MAX_LIGHTS];
 
-vec2 resolution = vec2(427.0, 240.0);
+// Grid vertices snap to, zero turns snapping off
+layout(location = 39) uniform vec2 snap_grid;
+// Depth at which the grid gets half as fine, zero keeps it the same at every depth
+layout(location = 40) uniform float snap_halving_distance;
+
+// Passed through the geometry shader, which may subdivide the triangle
+out vec2 vert_texture_coords;
+out vec3 vert_normal;
+// 0 no fog, 1 only fog color
+out float vert_fog_factor;
+out vec3 vert_light_color;
+out vec4 vert_vertex_color;
 
 // Position is post MVP translation of vertex
 vec4 to_low_precision(vec4 position, vec2 resolution) {
@@ -59,19 +84,85 @@ vec4 to_low_precision(vec4 position, vec2 resolution) {
 	return snapped;
 }
 
+// Gouraud shading, lights are summed per vertex and interpolated across the triangle
+vec3 get_light_color(vec3 world_position, vec3 world_normal) {
+    vec3 light_color = vec3(0.0);
+    for (int i = 0; i < light_count; i++) {
+        vec4 parameters = light_parameters[i];
+        vec3 to_light = light_positions[i] - world_position;
+        float light_distance = length(to_light);
+        if (light_distance >= parameters.x) {
+            continue;
+        }
+        vec3 light_direction = light_distance > 0.0 ? to_light / light_distance : world_normal;
+
+        float attenuation = pow(1.0 - light_distance / parameters.x, parameters.y);
+        // Point lights have both cosines at -1 and are never cut off
+        if (parameters.z > -1.0) {
+            float spot_cos = dot(-light_direction, light_directions[i]);
+            attenuation *= smoothstep(parameters.z, max(parameters.w, parameters.z + 0.0001), spot_cos);
+        }
+        float diffuse = max(dot(world_normal, light_direction), 0.0);
+        light_color += light_colors[i] * diffuse * attenuation;
+    }
+    return light_color;
+}
+
+float get_distance_fog_factor(float depth) {
+    if (fog_mode == 1) {
+        return 1.0 - exp(-fog_density * max(depth - fog_min, 0.0));
+    }
+    if (fog_mode == 2) {
+        float scaled_depth = fog_density * max(depth - fog_min, 0.0);
+        return 1.0 - exp(-scaled_depth * scaled_depth);
+    }
+    if (fog_mode == 3) {
+        return 0.0;
+    }
+
+    // Distances are set one at a time, so they can be in any order while being changed
+    float fog_start = min(fog_min, fog_max);
+    float fog_end = max(fog_min, fog_max);
+    // Fog distances were never set
+    if (fog_end <= 0.0) {
+        return 0.0;
+    }
+    // Hard edge when both distances are the same
+    if (fog_end - fog_start < 0.0001) {
+        return depth >= fog_end ? 1.0 : 0.0;
+    }
+    return clamp((depth - fog_start) / (fog_end - fog_start), 0.0, 1.0);
+}
+
+float get_height_fog_factor(float world_height) {
+    if (fog_height_thickness <= 0.0) {
+        return 1.0;
+    }
+    return clamp((fog_height_top - world_height) / fog_height_thickness, 0.0, 1.0);
+}
+
 void main(void) {
-	vec4 world_position = transformation_matrix * vec4(position, 1.0);
+	mat4 model_matrix = transformation_matrix * instance_transformation_matrix;
+	vec4 world_position = model_matrix * vec4(position, 1.0);
 	vec4 world_view = view_matrix * world_position;
 	vec4 projection_world_view = projection_matrix * world_view;
 	
     float depth = abs(world_view.z / world_view.w);
 	
-	projection_world_view = to_low_precision(projection_world_view, resolution);
+	if (snap_grid.x > 0.0 && snap_grid.y > 0.0) {
+	    vec2 grid = snap_grid;
+	    if (snap_halving_distance > 0.0) {
+	        grid = max(grid / (1.0 + depth / snap_halving_distance), vec2(1.0));
+	    }
+	    projection_world_view = to_low_precision(projection_world_view, grid);
+	}
     
 	gl_Position = projection_world_view;
 	
-    frag_texture_coords = texture_coords;
-    frag_normal = (transformation_matrix * vec4(normal, 0.0)).xyz;
+    vert_texture_coords = texture_coords;
+    vert_vertex_color = vertex_color;
+    vert_normal = (model_matrix * vec4(normal, 0.0)).xyz;
+    vert_light_color = get_light_color(world_position.xyz, normalize(vert_normal));
     
-    frag_fog_density = 1.0 - clamp((fog_max - depth) / (fog_min - fog_max), 0.0, 1.0);
+    vert_fog_factor = get_distance_fog_factor(depth) * get_height_fog_factor(world_position.y / world_position.w);
 }
\ No newline at end of file
@@ -0,0 +1,33 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+#version 450 core
+
+in vec2 frag_texture_coords;
+in vec4 frag_color;
+
+out vec4 color;
+
+// Untextured quads get a white texture
+layout(binding = 0) uniform sampler2D texture_sampler;
+
+void main(void) {
+    color = texture2D(texture_sampler, frag_texture_coords) * frag_color;
+}
\ No newline at end of file
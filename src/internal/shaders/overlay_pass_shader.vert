@@ -0,0 +1,37 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+#version 450 core
+
+// Sprite corners in framebuffer pixels, (0, 0) is the screen center
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec2 texture_coords;
+layout(location = 3) in vec4 color;
+
+layout(location = 0) uniform vec2 framebuffer_size;
+
+out vec2 frag_texture_coords;
+out vec4 frag_color;
+
+void main(void) {
+    gl_Position = vec4(position.xy * 2.0 / framebuffer_size, 0.0, 1.0);
+    frag_texture_coords = texture_coords;
+    frag_color = color;
+}
\ No newline at end of file
@@ -0,0 +1,46 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+#version 450 core
+
+const float PI = 3.14159265;
+
+in vec3 frag_direction;
+in vec2 frag_texture_coords;
+
+out vec4 color;
+
+// 0 texture per cube face, 1 equirectangular panorama
+layout(location = 8) uniform int panorama;
+
+layout(binding = 0) uniform sampler2D texture_sampler;
+
+void main(void) {
+    vec2 texture_coords = frag_texture_coords;
+    if (panorama == 1) {
+        vec3 direction = normalize(frag_direction);
+        // Image center is straight ahead down -z, seam is behind the camera
+        texture_coords = vec2(
+            atan(direction.x, -direction.z) / (2.0 * PI) + 0.5,
+            asin(clamp(direction.y, -1.0, 1.0)) / PI + 0.5
+        );
+    }
+    color = texture2D(texture_sampler, texture_coords);
+}
\ No newline at end of file
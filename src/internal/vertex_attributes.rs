@@ -19,12 +19,18 @@
 
 use std::mem;
 use gl::types::GLuint;
-use glam::{Vec2, Vec3};
+use glam::{Vec2, Vec3, Vec4};
 use crate::vertex_data::VertexData;
 
 pub(crate) const VERTEX_POSITION_ATTRIBUTE_ID: GLuint = 0;
 pub(crate) const VERTEX_TEXTURE_COORDINATE_ATTRIBUTE_ID: GLuint = 1;
 pub(crate) const VERTEX_NORMAL_ATTRIBUTE_ID: GLuint = 2;
+// Stored in its own buffer, only meshes with vertex colors have it. The rest read constant white, so the
+// shader takes the same path for both.
+pub(crate) const VERTEX_COLOR_ATTRIBUTE_ID: GLuint = 3;
+// Matrix attribute takes 4 locations, one per column (4 to 7)
+pub(crate) const VERTEX_INSTANCE_TRANSFORMATION_ATTRIBUTE_ID: GLuint = 4;
+pub(crate) const VERTEX_INSTANCE_TRANSFORMATION_COLUMNS: GLuint = 4;
 
 pub(crate) const VERTEX_DATA_ATTRIBUTES: &'static [GLuint] = &[VERTEX_POSITION_ATTRIBUTE_ID, VERTEX_TEXTURE_COORDINATE_ATTRIBUTE_ID, VERTEX_NORMAL_ATTRIBUTE_ID];
 
@@ -35,5 +41,6 @@ pub(crate) const VERTEX_DATA_NORMAL_OFFSET: GLuint = VERTEX_DATA_TEXTURE_COORDIN
 pub(crate) const VERTEX_POSITION_ATTRIBUTE_SIZE_IN_FLOATS: GLuint = mem::size_of::<Vec3>() as GLuint / mem::size_of::<f32>() as GLuint;
 pub(crate) const VERTEX_TEXTURE_COORDINATE_ATTRIBUTE_SIZE_IN_FLOATS: GLuint = mem::size_of::<Vec2>() as GLuint / mem::size_of::<f32>() as GLuint;
 pub(crate) const VERTEX_NORMAL_ATTRIBUTE_SIZE_IN_FLOATS: GLuint = mem::size_of::<Vec3>() as GLuint / mem::size_of::<f32>() as GLuint;
+pub(crate) const VERTEX_COLOR_ATTRIBUTE_SIZE_IN_FLOATS: GLuint = mem::size_of::<Vec4>() as GLuint / mem::size_of::<f32>() as GLuint;
 
 pub(crate) const VERTEX_DATA_SIZE: GLuint = mem::size_of::<VertexData>() as GLuint;
\ No newline at end of file
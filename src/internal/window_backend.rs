@@ -0,0 +1,37 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use crate::error::Error;
+use crate::graphics_settings::GraphicsSettings;
+use crate::input::Input;
+
+pub trait WindowBackend {
+    fn poll_input_events(&mut self, input: &mut Input);
+
+    fn swap_buffer(&mut self);
+
+    // Applies window related parts of the settings, fullscreen and vsync
+    fn apply_graphics_settings(&mut self, graphics_settings: &GraphicsSettings) -> Result<(), Error>;
+
+    fn get_display_size(&self) -> (i32, i32);
+
+    // Size of the area we draw into, in pixels
+    fn get_window_size(&self) -> (i32, i32);
+}
@@ -17,13 +17,17 @@
 // along with RSFX. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use sdl2::Sdl;
 use sdl2::VideoSubsystem;
-use sdl2::video::{GLContext, Window};
+use sdl2::video::{GLContext, Window, WindowPos};
 
+use crate::error::Error;
+use crate::graphics_settings::GraphicsSettings;
 use crate::input::Input;
+use crate::internal::window_backend::WindowBackend;
 
 pub struct WindowContext {
     sdl: Sdl,
@@ -32,12 +36,19 @@ pub struct WindowContext {
     gl_context: GLContext,
     display_width: i32,
     display_height: i32,
+    fullscreen: bool,
+    vsync: bool,
+    frame_rate_cap: Option<u32>,
+    last_swap: Instant,
 }
 
 impl WindowContext {
-    pub fn new(game_name: &str, vsync: bool, fullscreen: bool) -> Result<WindowContext, String> {
-        let sdl = sdl2::init().unwrap();
-        let video_subsystem = sdl.video().unwrap();
+    pub fn new(game_name: &str, graphics_settings: &GraphicsSettings, frame_rate_cap: Option<u32>) -> Result<WindowContext, Error> {
+        let vsync = graphics_settings.vsync;
+        let fullscreen = graphics_settings.fullscreen;
+
+        let sdl = sdl2::init().map_err(Error::WindowInit)?;
+        let video_subsystem = sdl.video().map_err(Error::WindowInit)?;
         let gl_attr = video_subsystem.gl_attr();
 
         gl_attr.set_context_major_version(4);
@@ -48,34 +59,35 @@ impl WindowContext {
         sdl.mouse().set_relative_mouse_mode(true);
 
         // Get primary display bounds
-        let current_display = video_subsystem.display_bounds(0)?;
+        let current_display = video_subsystem.display_bounds(0).map_err(Error::WindowInit)?;
         let display_width = current_display.width() as i32;
         let display_height = current_display.height() as i32;
 
+        let (window_width, window_height) = WindowContext::window_size_for(fullscreen, display_width, display_height);
         let window = match fullscreen {
             true => {
                 video_subsystem
-                    .window(game_name, display_width as u32, display_height as u32)
+                    .window(game_name, window_width, window_height)
                     .opengl()
                     .borderless()
                     .build()
-                    .unwrap()
+                    .map_err(|error| Error::WindowInit(error.to_string()))?
             }
             false => {
                 video_subsystem
-                    .window(game_name, (display_width as f32 * 0.9) as u32 , (display_height as f32 * 0.9) as u32 )
+                    .window(game_name, window_width, window_height)
                     .opengl()
                     .build()
-                    .unwrap()
+                    .map_err(|error| Error::WindowInit(error.to_string()))?
             }
         };
 
-        let gl_context = window.gl_create_context().unwrap();
+        let gl_context = window.gl_create_context().map_err(Error::GlInit)?;
 
         gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
 
         // Vsync
-        video_subsystem.gl_set_swap_interval(vsync as i32).unwrap();
+        video_subsystem.gl_set_swap_interval(vsync as i32).map_err(Error::GlInit)?;
 
         Ok(WindowContext {
             sdl,
@@ -84,9 +96,20 @@ impl WindowContext {
             gl_context,
             display_width,
             display_height,
+            fullscreen,
+            vsync,
+            frame_rate_cap,
+            last_swap: Instant::now(),
         })
     }
 
+    fn window_size_for(fullscreen: bool, display_width: i32, display_height: i32) -> (u32, u32) {
+        match fullscreen {
+            true => (display_width as u32, display_height as u32),
+            false => ((display_width as f32 * 0.9) as u32 , (display_height as f32 * 0.9) as u32 )
+        }
+    }
+
     pub fn get_display_width(&self) -> i32 {
         self.display_width
     }
@@ -94,8 +117,10 @@ impl WindowContext {
     pub fn get_display_height(&self) -> i32 {
         self.display_height
     }
+}
 
-    pub fn poll_input_events(&mut self, input: &mut Input) {
+impl WindowBackend for WindowContext {
+    fn poll_input_events(&mut self, input: &mut Input) {
         let mut event_pump = self.sdl.event_pump().unwrap();
         for event in event_pump.poll_iter() {
             match input.process_sdl_event(&event) {
@@ -105,8 +130,45 @@ impl WindowContext {
         }
     }
 
-    pub fn swap_buffer(&self) {
+    fn swap_buffer(&mut self) {
         self.window.gl_swap_window(); 
+
+        // Vsync already paces frames, cap is only applied without it
+        if let (false, Some(frame_rate_cap)) = (self.vsync, self.frame_rate_cap) {
+            let min_frame_time = Duration::from_secs_f64(1.0 / frame_rate_cap.max(1) as f64);
+            let frame_time = self.last_swap.elapsed();
+            if frame_time < min_frame_time {
+                thread::sleep(min_frame_time - frame_time);
+            }
+        }
+        self.last_swap = Instant::now();
+    }
+
+    fn apply_graphics_settings(&mut self, graphics_settings: &GraphicsSettings) -> Result<(), Error> {
+        if graphics_settings.fullscreen != self.fullscreen {
+            let (window_width, window_height) = WindowContext::window_size_for(graphics_settings.fullscreen, self.display_width, self.display_height);
+
+            self.window.set_bordered(!graphics_settings.fullscreen);
+            self.window.set_size(window_width, window_height).map_err(|error| Error::WindowInit(error.to_string()))?;
+            self.window.set_position(WindowPos::Centered, WindowPos::Centered);
+            self.fullscreen = graphics_settings.fullscreen;
+        }
+
+        if graphics_settings.vsync != self.vsync {
+            self.video_subsystem.gl_set_swap_interval(graphics_settings.vsync as i32).map_err(Error::GlInit)?;
+            self.vsync = graphics_settings.vsync;
+        }
+
+        Ok(())
+    }
+
+    fn get_display_size(&self) -> (i32, i32) {
+        (self.display_width, self.display_height)
+    }
+
+    fn get_window_size(&self) -> (i32, i32) {
+        let (width, height) = self.window.drawable_size();
+        (width as i32, height as i32)
     }
 }
 
@@ -38,108 +38,204 @@ pub mod vertex_data;
 pub mod transform;
 pub mod transform_animation;
 pub mod graphics_settings;
-
-use crate::scene::Scene;
+pub mod engine_settings;
+pub mod error;
+pub mod clock;
+pub mod headless;
+pub mod screenshot;
+pub mod light;
+pub mod uniform_value;
+pub mod post_process;
+pub mod material;
+pub mod sprite;
+pub mod bitmap_font;
+pub mod particles;
+pub mod background;
+pub mod render_target;
+
+use crate::clock::Clock;
+use crate::scene::{Scene, SceneStack};
+use crate::engine_settings::EngineSettings;
+use crate::error::Error;
 use crate::game_status::GameStatus;
-use crate::graphics_settings::{GraphicsSettings, RenderResolution};
+use crate::graphics_settings::GraphicsSettings;
+use crate::headless::HeadlessReport;
 use crate::input::Input;
 use crate::internal::gl_renderer::GlRenderer;
-use crate::internal::time;
+use crate::internal::null_window::NullWindow;
+use crate::internal::render_backend::RenderBackend;
+use crate::internal::window_backend::WindowBackend;
 use crate::internal::window_context::WindowContext;
 use crate::renderer::Renderer;
 
-// 60 ticks per second
-const TICKS_PER_SECOND: u32 = 60;
-const TICK_RATE: u128 = (1.0f64 / TICKS_PER_SECOND as f64 * 1000.0f64) as u128;
+// Display size pretended by headless runs, used when Native render resolution is requested
+const HEADLESS_DISPLAY_WIDTH: i32 = 1920;
+const HEADLESS_DISPLAY_HEIGHT: i32 = 1080;
 
-pub struct Rsfx;
+pub struct Rsfx {
+    clock: Option<Clock>,
+}
 
 impl Rsfx {
     pub fn new() -> Rsfx {
-        Rsfx { }
+        Rsfx { clock: None }
+    }
+
+    // Runs the game on the given clock instead of the default monotonic one
+    pub fn with_clock(clock: Clock) -> Rsfx {
+        Rsfx { clock: Some(clock) }
     }
 
     // This func is mutable to ensure that this object is not used more than once when game is running
-    pub fn run(&mut self, game_name: &str, graphics_settings: GraphicsSettings, starting_scene: Box<dyn Scene>) -> Result<(), String> {
-        let mut window_context = WindowContext::new(game_name, graphics_settings.vsync, graphics_settings.fullscreen)?;
-
-        let display_width = window_context.get_display_width();
-        let display_height = window_context.get_display_height();
-
-        let framebuffer_width = match graphics_settings.render_resolution {
-            RenderResolution::W427h240 => 427,
-            RenderResolution::W640h360 => 640,
-            RenderResolution::W854h480 => 854,
-            RenderResolution::Native => display_width
-        };
-        let framebuffer_height = match graphics_settings.render_resolution {
-            RenderResolution::W427h240 => 240,
-            RenderResolution::W640h360 => 360,
-            RenderResolution::W854h480 => 480,
-            RenderResolution::Native => display_height
-        };
-
-        let gl_renderer = GlRenderer::new(framebuffer_width, framebuffer_height, display_width, display_height);
-
-        let mut renderer = Renderer::new(gl_renderer);
-        renderer.set_clear_color(0.0, 0.0, 0.0);
+    pub fn run(&mut self, game_name: &str, graphics_settings: GraphicsSettings, engine_settings: EngineSettings, starting_scene: Box<dyn Scene>) -> Result<(), Error> {
+        let mut window_context = WindowContext::new(game_name, &graphics_settings, engine_settings.frame_rate_cap)?;
+
+        let (display_width, display_height) = window_context.get_display_size();
+        let (window_width, window_height) = window_context.get_window_size();
+
+        let (framebuffer_width, framebuffer_height) = graphics_settings.render_resolution.get_size(display_width, display_height);
+
+        let gl_renderer = GlRenderer::new(framebuffer_width, framebuffer_height, window_width, window_height)?;
+
+        let mut renderer = Renderer::new(RenderBackend::Gl(Box::new(gl_renderer)));
+        renderer.set_graphics_settings(&graphics_settings);
+
+        let mut clock = self.clock.take().unwrap_or_else(Clock::new);
+        clock.tick();
+        let mut frame_timer = || clock.tick();
+
+        Rsfx::main_loop(&mut window_context, &mut renderer, graphics_settings, &engine_settings, starting_scene, &mut frame_timer, None)?;
+
+        Ok(())
+    }
+
+    // Runs the game for the given number of ticks without a window or GPU. Every frame advances exactly one tick,
+    // input_script gets to modify the input before each of them and everything the scenes submit to the renderer
+    // is recorded instead of drawn. There is no GL context, meshes, textures and render targets scenes create get
+    // placeholder ids and hold no data. Material shaders can't be created.
+    pub fn run_headless<F>(&mut self, graphics_settings: GraphicsSettings, engine_settings: EngineSettings, starting_scene: Box<dyn Scene>, ticks: u64, input_script: F) -> Result<HeadlessReport, Error>
+        where F: FnMut(u64, &mut Input)
+    {
+        // Declared first so it ends last, after the renderer and scenes release their resources
+        let _headless_scope = RenderBackend::enter_headless();
+
+        let mut null_window = NullWindow::new(HEADLESS_DISPLAY_WIDTH, HEADLESS_DISPLAY_HEIGHT, input_script);
+
+        let mut renderer = Renderer::new(RenderBackend::Recording(vec![]));
+
+        let tick_duration = engine_settings.get_tick_duration();
+        let mut frame_timer = || tick_duration;
+
+        let (game_status, ticks_run) = Rsfx::main_loop(&mut null_window, &mut renderer, graphics_settings, &engine_settings, starting_scene, &mut frame_timer, Some(ticks))?;
+
+        Ok(HeadlessReport::new(game_status, ticks_run, renderer.take_recorded_frames()))
+    }
+
+    // frame_timer returns seconds passed since its previous call
+    fn main_loop(
+        window: &mut dyn WindowBackend,
+        renderer: &mut Renderer,
+        graphics_settings: GraphicsSettings,
+        engine_settings: &EngineSettings,
+        starting_scene: Box<dyn Scene>,
+        frame_timer: &mut dyn FnMut() -> f64,
+        tick_limit: Option<u64>
+    ) -> Result<(GameStatus, u64), Error> {
+        let tick_duration = engine_settings.get_tick_duration();
+        let max_frame_time = engine_settings.get_max_ticks_per_frame() as f64 * tick_duration;
+
+        let (display_width, display_height) = window.get_display_size();
+        let (window_width, window_height) = window.get_window_size();
+        let (framebuffer_width, framebuffer_height) = graphics_settings.render_resolution.get_size(display_width, display_height);
 
         let mut input = Input::new(
             framebuffer_width,
             framebuffer_height,
-            display_width,
-            display_height
+            window_width,
+            window_height
         );
 
-        let mut current_scene = starting_scene;
+        let (clear_r, clear_g, clear_b) = engine_settings.clear_color;
+        renderer.set_clear_color(clear_r, clear_g, clear_b);
 
-        current_scene.on_start(&mut renderer);
+        let mut scene_stack = SceneStack::new(starting_scene, renderer);
 
-        let delta_time = time::time_now();
-        let mut last_frame_time = delta_time;
+        let mut accumulator = 0.0;
+        let mut ticks_run = 0;
 
         let mut game_status = GameStatus::new();
+        game_status.set_applied_graphics_settings(graphics_settings);
         'main_loop: loop {
-            window_context.poll_input_events(&mut input);
+            accumulator += frame_timer().min(max_frame_time);
+
+            window.poll_input_events(&mut input);
 
             if input.should_quit() {
+                scene_stack.clear();
                 break 'main_loop;
             }
 
-            let time_now = time::time_now();
-            if time_now >= last_frame_time + TICK_RATE {
-                let delta_time = time_now - last_frame_time;
-                last_frame_time = time_now;
-
-                // Update scene
-                match current_scene.on_update(
-                    &mut game_status,
-                    &mut renderer,
-                    &input,
-                    delta_time as f64 / 1000.0
-                ) {
-                    Some(scene) => {
-                        current_scene.on_destroy();
-                        current_scene = scene;
-                        current_scene.on_start(&mut renderer);
-                    }
-                    _ => {
-                        if game_status.should_quit() {
-                            current_scene.on_destroy();
-                            break 'main_loop
+            while accumulator >= tick_duration {
+                if tick_limit.is_some_and(|limit| ticks_run >= limit) {
+                    scene_stack.clear();
+                    break 'main_loop
+                }
+
+                accumulator -= tick_duration;
+                ticks_run += 1;
+
+                // Update scenes
+                let delta_time = game_status.scale_delta_time(tick_duration);
+                scene_stack.update(&mut game_status, renderer, &input, delta_time);
+
+                if game_status.should_quit() {
+                    scene_stack.clear();
+                    break 'main_loop
+                }
+
+                // Last scene was popped, nothing left to run
+                if scene_stack.is_empty() {
+                    break 'main_loop
+                }
+
+                if let Some(requested_settings) = game_status.take_requested_graphics_settings() {
+                    let current_settings = *game_status.get_graphics_settings();
+                    match Rsfx::apply_graphics_settings(window, renderer, &mut input, &requested_settings) {
+                        Ok(_) => game_status.set_applied_graphics_settings(requested_settings),
+                        Err(error) => {
+                            // Go back to what was working, game can't continue if even that fails
+                            game_status.set_graphics_settings_error(error);
+                            Rsfx::apply_graphics_settings(window, renderer, &mut input, &current_settings)?;
                         }
                     }
-                };
+                }
 
+                // Cursor movement is consumed by the first tick, catch-up ticks must not apply it again
                 input.clear_states();
             }
 
-            current_scene.on_render(&mut renderer);
+            // How far we are between the last tick and the next one, used to interpolate rendered state
+            let alpha = accumulator / tick_duration;
+            scene_stack.render(renderer, alpha);
 
             renderer.run_passes();
-            window_context.swap_buffer();
+            window.swap_buffer();
         }
 
+        Ok((game_status, ticks_run))
+    }
+
+    fn apply_graphics_settings(window: &mut dyn WindowBackend, renderer: &mut Renderer, input: &mut Input, graphics_settings: &GraphicsSettings) -> Result<(), Error> {
+        window.apply_graphics_settings(graphics_settings)?;
+
+        let (display_width, display_height) = window.get_display_size();
+        let (window_width, window_height) = window.get_window_size();
+        let (framebuffer_width, framebuffer_height) = graphics_settings.render_resolution.get_size(display_width, display_height);
+
+        renderer.resize(framebuffer_width, framebuffer_height, window_width, window_height)?;
+        renderer.set_graphics_settings(graphics_settings);
+        input.set_screen_size(framebuffer_width, framebuffer_height, window_width, window_height);
+
         Ok(())
     }
 }
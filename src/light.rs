@@ -0,0 +1,101 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use glam::Vec3;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum LightKind {
+    Point,
+    // Lights a cone around the direction, angles are in radians from the direction. Light fades out
+    // between the inner and the outer angle.
+    Spot { direction: Vec3, inner_angle: f32, outer_angle: f32 },
+}
+
+// Positioned light, evaluated per vertex like on PSX. Brightness goes from full at the light position to
+// zero at range, falloff shapes that curve (1.0 is linear, higher values fade out faster).
+#[derive(Copy, Clone, PartialEq)]
+pub struct Light {
+    kind: LightKind,
+    position: Vec3,
+    color: Vec3,
+    range: f32,
+    falloff: f32,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct LightId(pub(crate) usize);
+
+impl Light {
+    pub fn point(position: Vec3, color: Vec3, range: f32) -> Light {
+        Light { kind: LightKind::Point, position, color, range: range.max(0.0), falloff: 1.0 }
+    }
+
+    pub fn spot(position: Vec3, direction: Vec3, color: Vec3, range: f32, inner_angle: f32, outer_angle: f32) -> Light {
+        let mut light = Light::point(position, color, range);
+        light.set_kind(LightKind::Spot { direction, inner_angle, outer_angle });
+        light
+    }
+
+    pub fn get_kind(&self) -> &LightKind {
+        &self.kind
+    }
+
+    pub fn set_kind(&mut self, kind: LightKind) {
+        self.kind = match kind {
+            LightKind::Point => kind,
+            LightKind::Spot { direction, inner_angle, outer_angle } => {
+                let outer_angle = outer_angle.max(0.0);
+                LightKind::Spot { direction: direction.normalize_or_zero(), inner_angle: inner_angle.clamp(0.0, outer_angle), outer_angle }
+            }
+        };
+    }
+
+    pub fn get_position(&self) -> &Vec3 {
+        &self.position
+    }
+
+    pub fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+    }
+
+    pub fn get_color(&self) -> &Vec3 {
+        &self.color
+    }
+
+    pub fn set_color(&mut self, color: Vec3) {
+        self.color = color;
+    }
+
+    pub fn get_range(&self) -> f32 {
+        self.range
+    }
+
+    pub fn set_range(&mut self, range: f32) {
+        self.range = range.max(0.0);
+    }
+
+    pub fn get_falloff(&self) -> f32 {
+        self.falloff
+    }
+
+    pub fn set_falloff(&mut self, falloff: f32) {
+        self.falloff = falloff.max(0.0);
+    }
+}
@@ -0,0 +1,135 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use std::rc::Rc;
+
+use gl::types::GLuint;
+
+use crate::error::Error;
+use crate::internal::shader_program::ShaderProgram;
+use crate::renderer::BlendMode;
+use crate::texture::Texture;
+use crate::uniform_value::UniformValue;
+
+pub const MAX_MATERIAL_TEXTURES: usize = 4;
+
+// Shader program created with Renderer::create_material_shader. The engine keeps setting its uniforms
+// (matrices, fog, lights, ...) in it as long as they are declared the same way as in the built-in shader,
+// with the same name and layout location. Texture slot N is bound to binding N.
+#[derive(Clone)]
+pub struct MaterialShader {
+    program: Rc<ShaderProgram>,
+    // Bit per engine uniform location, set when the program declares that uniform
+    engine_uniform_mask: u64,
+}
+
+// Everything a draw needs besides the mesh. Materials are cheap to clone, clones share the shader and
+// textures. Textures stay alive as long as a material uses them.
+#[derive(Clone)]
+pub struct Material {
+    // Built-in shader when not set
+    shader: Option<MaterialShader>,
+    textures: [Option<Rc<Texture>>; MAX_MATERIAL_TEXTURES],
+    // Name, location in the shader and value
+    uniforms: Vec<(String, i32, UniformValue)>,
+    blend_mode: BlendMode,
+}
+
+impl MaterialShader {
+    pub(crate) fn new(program: ShaderProgram, engine_uniform_mask: u64) -> MaterialShader {
+        MaterialShader { program: Rc::new(program), engine_uniform_mask }
+    }
+}
+
+impl Material {
+    // Built-in shader drawing the texture
+    pub fn new(texture: &Rc<Texture>) -> Material {
+        let mut textures: [Option<Rc<Texture>>; MAX_MATERIAL_TEXTURES] = Default::default();
+        textures[0] = Some(texture.clone());
+        Material { shader: None, textures, uniforms: vec![], blend_mode: BlendMode::Opaque }
+    }
+
+    pub fn with_shader(shader: &MaterialShader) -> Material {
+        Material { shader: Some(shader.clone()), textures: Default::default(), uniforms: vec![], blend_mode: BlendMode::Opaque }
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    pub fn set_texture(&mut self, slot: usize, texture: &Rc<Texture>) -> Result<(), Error> {
+        match self.textures.get_mut(slot) {
+            Some(slot_texture) => {
+                *slot_texture = Some(texture.clone());
+                Ok(())
+            }
+            None => Err(Error::LimitReached(format!("materials have only {} texture slots", MAX_MATERIAL_TEXTURES)))
+        }
+    }
+
+    pub fn clear_texture(&mut self, slot: usize) {
+        if let Some(slot_texture) = self.textures.get_mut(slot) {
+            *slot_texture = None;
+        }
+    }
+
+    // Only materials with their own shader have named uniforms. Uniforms the shader doesn't use are kept,
+    // but never set.
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) {
+        if let Some((_, _, existing_value)) = self.uniforms.iter_mut().find(|(uniform_name, _, _)| uniform_name == name) {
+            *existing_value = value;
+            return;
+        }
+
+        let location = match &self.shader {
+            Some(shader) => shader.program.get_uniform_location(name),
+            None => -1
+        };
+        self.uniforms.push((String::from(name), location, value));
+    }
+
+    pub fn remove_uniform(&mut self, name: &str) {
+        self.uniforms.retain(|(uniform_name, _, _)| uniform_name != name);
+    }
+
+    pub(crate) fn get_program_id(&self) -> Option<GLuint> {
+        self.shader.as_ref().map(|shader| shader.program.id())
+    }
+
+    pub(crate) fn get_engine_uniform_mask(&self) -> u64 {
+        self.shader.as_ref().map(|shader| shader.engine_uniform_mask).unwrap_or(u64::MAX)
+    }
+
+    // 0 for empty slots
+    pub(crate) fn get_texture_ids(&self) -> [GLuint; MAX_MATERIAL_TEXTURES] {
+        self.textures.each_ref().map(|texture| texture.as_ref().map(|texture| texture.texture_id()).unwrap_or(0))
+    }
+
+    // Uniforms the shader has, by location
+    pub(crate) fn get_uniforms(&self) -> impl Iterator<Item = (i32, UniformValue)> + '_ {
+        self.uniforms.iter()
+            .filter(|(_, location, _)| *location >= 0)
+            .map(|(_, location, value)| (*location, *value))
+    }
+}
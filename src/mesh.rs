@@ -21,20 +21,77 @@ use std::mem;
 use std::ffi::c_void;
 use std::ptr::null;
 
-use gl::types::{GLuint, GLsizei, GLint, GLfloat};
+use gl::types::{GLenum, GLuint, GLsizei, GLint, GLfloat};
+use glam::Vec4;
+use crate::internal::render_backend::RenderBackend;
 use crate::internal::vertex_attributes;
 
 use crate::vertex_data::VertexData;
 
+#[derive(Clone)]
+pub enum MeshIndices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl MeshIndices {
+    // Picks the smallest index type that can address every vertex
+    pub fn compact(indices: Vec<u32>) -> MeshIndices {
+        if indices.iter().all(|index| *index <= u16::MAX as u32) {
+            return MeshIndices::U16(indices.iter().map(|index| *index as u16).collect());
+        }
+        MeshIndices::U32(indices)
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            MeshIndices::U16(indices) => indices.len(),
+            MeshIndices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, idx: usize) -> usize {
+        match self {
+            MeshIndices::U16(indices) => indices[idx] as usize,
+            MeshIndices::U32(indices) => indices[idx] as usize,
+        }
+    }
+
+    pub(crate) fn gl_type(&self) -> GLenum {
+        match self {
+            MeshIndices::U16(_) => gl::UNSIGNED_SHORT,
+            MeshIndices::U32(_) => gl::UNSIGNED_INT,
+        }
+    }
+}
+
 pub struct MeshData {
     vertex_data: Vec<VertexData>,
+    indices: Option<MeshIndices>,
+    colors: Option<Vec<Vec4>>,
 }
 
 impl MeshData {
     pub fn from_data(vertex_data: Vec<VertexData>) -> MeshData {
-        MeshData { vertex_data }
+        MeshData { vertex_data, indices: None, colors: None }
+    }
+
+    // Every 3 indices form a triangle
+    pub fn from_indexed_data(vertex_data: Vec<VertexData>, indices: MeshIndices) -> MeshData {
+        MeshData { vertex_data, indices: Some(indices), colors: None }
+    }
+
+    // RGBA per stored vertex, multiplies texture color. Meshes without them are drawn white.
+    pub fn with_colors(self, colors: Vec<Vec4>) -> MeshData {
+        MeshData { colors: Some(colors), ..self }
     }
     
+    // Stored vertices. For indexed data these are the unique ones in no particular triangle order, use
+    // get_elements_count and get_element to walk triangles.
     pub fn get_vertices(&self) -> &Vec<VertexData> {
         &self.vertex_data
     }
@@ -42,20 +99,87 @@ impl MeshData {
     pub fn get_vertices_count(&self) -> GLsizei {
         self.vertex_data.len() as GLsizei
     }
+
+    pub fn get_indices(&self) -> Option<&MeshIndices> {
+        self.indices.as_ref()
+    }
+
+    pub fn get_colors(&self) -> Option<&Vec<Vec4>> {
+        self.colors.as_ref()
+    }
+
+    // Number of vertices drawn, every 3 of them form a triangle
+    pub fn get_elements_count(&self) -> usize {
+        match &self.indices {
+            Some(indices) => indices.len(),
+            None => self.vertex_data.len()
+        }
+    }
+
+    // Vertex drawn at the given position, resolved through indices if the mesh has them
+    pub fn get_element(&self, idx: usize) -> &VertexData {
+        match &self.indices {
+            Some(indices) => &self.vertex_data[indices.get(idx)],
+            None => &self.vertex_data[idx]
+        }
+    }
 }
 
 pub struct Mesh {
     vao_id: GLuint,
     vbo_ids: Vec<GLuint>,
     vertices_count: GLsizei,
+    index_type: Option<GLenum>,
+    // Also in vbo_ids, None while the mesh has no vertex colors
+    color_vbo_id: Option<GLuint>,
+    // Created in a headless run, there is no GL object behind vao_id
+    placeholder: bool,
 }
 
 impl Mesh {
     pub fn from_mesh_data(mesh_data: &MeshData) -> Mesh {
-        Mesh::from_raw_data(mesh_data.get_vertices())
+        let mut mesh = match mesh_data.get_indices() {
+            Some(indices) => Mesh::from_indexed_raw_data(mesh_data.get_vertices(), indices),
+            None => Mesh::from_raw_data(mesh_data.get_vertices())
+        };
+
+        if let Some(colors) = mesh_data.get_colors() {
+            mesh.update_color_data(colors, gl::STATIC_DRAW);
+        }
+        mesh
+    }
+
+    pub fn from_indexed_raw_data(vertex_data: &Vec<VertexData>, indices: &MeshIndices) -> Mesh {
+        let mut mesh = Mesh::from_raw_data(vertex_data);
+        if mesh.placeholder {
+            mesh.vertices_count = indices.len() as GLsizei;
+            mesh.index_type = Some(indices.gl_type());
+            return mesh;
+        }
+
+        unsafe {
+            gl::BindVertexArray(mesh.vao_id);
+        }
+
+        // Element buffer binding is part of vertex array state, so it stays bound to it
+        mesh.vbo_ids.push(Mesh::bind_indices_buffer(indices));
+
+        unsafe {
+            gl::BindVertexArray(0);
+        }
+
+        mesh.vertices_count = indices.len() as GLsizei;
+        mesh.index_type = Some(indices.gl_type());
+        mesh
     }
     
     pub fn from_raw_data(vertex_data: &Vec<VertexData>) -> Mesh {
+        let vertices_count = vertex_data.len() as GLsizei;
+        // Headless run, nothing will draw the mesh
+        if RenderBackend::is_headless() {
+            return Mesh { vao_id: RenderBackend::next_placeholder_id(), vbo_ids: vec![], vertices_count, index_type: None, color_vbo_id: None, placeholder: true };
+        }
+
         let vao_id = {
             let mut vao_ids = vec![0];
             unsafe {
@@ -81,11 +205,10 @@ impl Mesh {
             gl::BindVertexArray(0);
         }
 
-        let vertices_count = vertex_data.len() as GLsizei;
-        Mesh { vao_id, vbo_ids, vertices_count}
+        Mesh { vao_id, vbo_ids, vertices_count, index_type: None, color_vbo_id: None, placeholder: false }
     }
 
-    fn bind_indices_buffer(indices: &Vec<GLuint>) -> GLuint {
+    fn bind_indices_buffer(indices: &MeshIndices) -> GLuint {
         let vbo_id = {
             let mut vbo_ids = vec![0];
             unsafe {
@@ -96,7 +219,14 @@ impl Mesh {
 
         unsafe {
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, vbo_id);
-            gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, (mem::size_of::<GLuint>() * indices.len()) as isize, indices.as_ptr() as *const c_void, gl::STATIC_DRAW);
+            match indices {
+                MeshIndices::U16(indices) => {
+                    gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, (mem::size_of::<u16>() * indices.len()) as isize, indices.as_ptr() as *const c_void, gl::STATIC_DRAW);
+                }
+                MeshIndices::U32(indices) => {
+                    gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, (mem::size_of::<u32>() * indices.len()) as isize, indices.as_ptr() as *const c_void, gl::STATIC_DRAW);
+                }
+            }
         }
 
         vbo_id
@@ -163,17 +293,76 @@ impl Mesh {
         vbo_id
     }
 
+    // Replaces vertices and their colors of a mesh without indices, for geometry that is rebuilt every frame
+    pub(crate) fn update_vertex_data(&mut self, vertex_data: &[VertexData], colors: &[Vec4]) {
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo_ids[0]);
+            gl::BufferData(gl::ARRAY_BUFFER, mem::size_of_val(vertex_data) as isize, vertex_data.as_ptr() as *const c_void, gl::STREAM_DRAW);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        self.update_color_data(colors, gl::STREAM_DRAW);
+        self.vertices_count = vertex_data.len() as GLsizei;
+    }
+
+    // Color buffer is created on first use, until then the attribute is disabled and the shader reads
+    // the constant white GlRenderer sets
+    fn update_color_data(&mut self, colors: &[Vec4], usage: GLenum) {
+        if self.placeholder {
+            return;
+        }
+
+        let vbo_id = match self.color_vbo_id {
+            Some(vbo_id) => vbo_id,
+            None => {
+                let mut vbo_id = 0;
+                unsafe {
+                    gl::GenBuffers(1, &mut vbo_id);
+                    gl::BindVertexArray(self.vao_id);
+                    gl::BindBuffer(gl::ARRAY_BUFFER, vbo_id);
+                    gl::EnableVertexAttribArray(vertex_attributes::VERTEX_COLOR_ATTRIBUTE_ID);
+                    gl::VertexAttribPointer(
+                        vertex_attributes::VERTEX_COLOR_ATTRIBUTE_ID,
+                        vertex_attributes::VERTEX_COLOR_ATTRIBUTE_SIZE_IN_FLOATS as i32,
+                        gl::FLOAT,
+                        gl::FALSE,
+                        0,
+                        null()
+                    );
+                    gl::BindVertexArray(0);
+                }
+                self.vbo_ids.push(vbo_id);
+                self.color_vbo_id = Some(vbo_id);
+                vbo_id
+            }
+        };
+
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo_id);
+            gl::BufferData(gl::ARRAY_BUFFER, mem::size_of_val(colors) as isize, colors.as_ptr() as *const c_void, usage);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+    }
+
     pub fn vao_id(&self) -> GLuint {
         self.vao_id
     }
 
+    // For indexed meshes this is the number of indices
     pub fn vertices_count(&self) -> GLsizei {
         self.vertices_count
     }
+
+    pub fn index_type(&self) -> Option<GLenum> {
+        self.index_type
+    }
 }
 
 impl Drop for Mesh {
     fn drop(&mut self) {
+        if self.placeholder {
+            return;
+        }
+
         unsafe {
             gl::DeleteBuffers(self.vbo_ids.len() as GLsizei, self.vbo_ids.as_ptr());
             let temp_vec = vec![self.vao_id];
@@ -17,59 +17,76 @@
 // along with RSFX. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use std::collections::HashMap;
 use std::str::FromStr;
-use glam::{Vec3, Vec2};
+use glam::{Vec3, Vec2, Vec4};
 
-use crate::mesh::{Mesh, MeshData};
+use crate::error::Error;
+use crate::mesh::{Mesh, MeshData, MeshIndices};
 use crate::vertex_data::VertexData;
 
-pub fn load_obj_data(obj_data: &str) -> MeshData {
+// Returned data is indexed, vertices shared by faces are stored once. Walk triangles with
+// MeshData::get_element rather than get_vertices.
+pub fn load_obj_data(obj_data: &str) -> Result<MeshData, Error> {
     let lines: Vec<_> = obj_data.lines().collect();
 
     let mut vertices: Vec<Vec3> = vec![];
+    let mut vertex_colors: Vec<Option<Vec4>> = vec![];
     let mut texture_coords: Vec<Vec2> = vec![];
     let mut normals: Vec<Vec3> = vec![];
 
-    for line in &lines {
+    for (line_idx, line) in lines.iter().enumerate() {
+        let line_number = line_idx + 1;
         let mut splits: Vec<_> = line.split_whitespace().collect();
         splits.retain(|&x| x.len() != 0);
 
         if line.starts_with("v ") {
             vertices.push(
                 Vec3::new(
-                    f32::from_str(splits[1].trim()).unwrap(),
-                    f32::from_str(splits[2].trim()).unwrap(),
-                    f32::from_str(splits[3].trim()).unwrap()
+                    parse_float(&splits, 1, line_number)?,
+                    parse_float(&splits, 2, line_number)?,
+                    parse_float(&splits, 3, line_number)?
                 )
             );
+            vertex_colors.push(parse_vertex_color(&splits, line_number)?);
         }
         else if line.starts_with("vt ") {
             texture_coords.push(
                 Vec2::new(
-                    f32::from_str(splits[1].trim()).unwrap(),
-                    f32::from_str(splits[2].trim()).unwrap()
+                    parse_float(&splits, 1, line_number)?,
+                    parse_float(&splits, 2, line_number)?
                 )
             );
         }
         else if line.starts_with("vn ") {
             normals.push(
                 Vec3::new(
-                    f32::from_str(splits[1].trim()).unwrap(),
-                    f32::from_str(splits[2].trim()).unwrap(),
-                    f32::from_str(splits[3].trim()).unwrap()
+                    parse_float(&splits, 1, line_number)?,
+                    parse_float(&splits, 2, line_number)?,
+                    parse_float(&splits, 3, line_number)?
                 )
             );
         }
     }
 
     let mut vertex_data: Vec<VertexData> = vec![];
+    let mut colors: Vec<Vec4> = vec![];
+    let mut indices: Vec<u32> = vec![];
+    // Face vertices repeat a lot, each unique position/texture/normal combination is stored once.
+    // Keyed on parsed indices, '1/2/3' and '01/2/3' are the same vertex.
+    let mut vertex_lookup: HashMap<(usize, usize, usize), u32> = HashMap::new();
 
-    for line in &lines {
+    for (line_idx, line) in lines.iter().enumerate() {
+        let line_number = line_idx + 1;
         if line.starts_with("f ") {
             let mut splits: Vec<_> = line.split_whitespace().collect();
             splits.retain(|&x| x.len() != 0);
             splits.remove(0);
 
+            if splits.len() < 3 {
+                return Err(Error::asset_decode(Some(line_number), "Face has less than 3 vertices"));
+            }
+
             let mut faces = vec![[0, 1, 2]];
 
             // triangulate face that has more than 3 vertices
@@ -82,28 +99,189 @@ pub fn load_obj_data(obj_data: &str) -> MeshData {
             for face in faces {
                 for vert_idx in face {
                     let vertex_info = splits[vert_idx];
-
                     let mut vertex_indices: Vec<&str> = vertex_info.split('/').collect();
                     vertex_indices.retain(|&x| x.len() != 0);
 
-                    let vertex_index = i32::from_str(vertex_indices[0]).unwrap() - 1;
-                    let texture_coord_index = i32::from_str(vertex_indices[1]).unwrap() - 1;
-                    let normal_index = i32::from_str(vertex_indices[2]).unwrap() - 1;
+                    if vertex_indices.len() != 3 {
+                        return Err(Error::asset_decode(
+                            Some(line_number),
+                            &format!("Face vertex '{}' is not in position/texture/normal index format", vertex_info)
+                        ));
+                    }
 
-                    let vertex = VertexData::new(
-                        vertices[vertex_index as usize],
-                        texture_coords[texture_coord_index as usize],
-                        normals[normal_index as usize]
+                    let key = (
+                        parse_index(&vertices, vertex_indices[0], line_number)?,
+                        parse_index(&texture_coords, vertex_indices[1], line_number)?,
+                        parse_index(&normals, vertex_indices[2], line_number)?
                     );
-                    vertex_data.push(vertex);
+
+                    if let Some(index) = vertex_lookup.get(&key) {
+                        indices.push(*index);
+                        continue;
+                    }
+
+                    let index = vertex_data.len() as u32;
+                    vertex_data.push(VertexData::new(vertices[key.0], texture_coords[key.1], normals[key.2]));
+                    colors.push(vertex_colors[key.0].unwrap_or(Vec4::ONE));
+                    vertex_lookup.insert(key, index);
+                    indices.push(index);
                 }
             }
         }
     }
 
-    MeshData::from_data(vertex_data)
+    let mesh_data = MeshData::from_indexed_data(vertex_data, MeshIndices::compact(indices));
+    if vertex_colors.iter().any(|color| color.is_some()) {
+        return Ok(mesh_data.with_colors(colors));
+    }
+    Ok(mesh_data)
+}
+
+pub fn load_obj_mesh(obj_data: &str) -> Result<Mesh, Error> {
+    Ok(Mesh::from_mesh_data(&load_obj_data(obj_data)?))
+}
+
+fn parse_float(splits: &[&str], idx: usize, line_number: usize) -> Result<f32, Error> {
+    let value = splits.get(idx)
+        .ok_or_else(|| Error::asset_decode(Some(line_number), &format!("Expected at least {} values", idx)))?;
+
+    f32::from_str(value.trim())
+        .map_err(|_| Error::asset_decode(Some(line_number), &format!("'{}' is not a number", value)))
+}
+
+// Vertex color is a common OBJ extension, 'v x y z r g b' with optional alpha and components in 0..1 range
+// None for vertices without it or with only a w component, those are white if other vertices have color.
+fn parse_vertex_color(splits: &[&str], line_number: usize) -> Result<Option<Vec4>, Error> {
+    match splits.len() {
+        4 | 5 => return Ok(None),
+        7 | 8 => {}
+        _ => return Err(Error::asset_decode(Some(line_number), "Vertex color needs 3 or 4 components"))
+    }
+
+    let alpha = if splits.len() > 7 { parse_float(splits, 7, line_number)? } else { 1.0 };
+    Ok(Some(Vec4::new(
+        parse_float(splits, 4, line_number)?,
+        parse_float(splits, 5, line_number)?,
+        parse_float(splits, 6, line_number)?,
+        alpha
+    )))
 }
 
-pub fn load_obj_mesh(obj_data: &str) -> Mesh {
-    Mesh::from_mesh_data(&load_obj_data(obj_data))
-}
\ No newline at end of file
+// OBJ indices are 1-based, returns the 0-based index into values
+fn parse_index<T>(values: &[T], index: &str, line_number: usize) -> Result<usize, Error> {
+    let parsed_index = usize::from_str(index)
+        .map_err(|_| Error::asset_decode(Some(line_number), &format!("'{}' is not a valid index", index)))?;
+
+    parsed_index.checked_sub(1)
+        .filter(|idx| *idx < values.len())
+        .ok_or_else(|| Error::asset_decode(Some(line_number), &format!("Index {} is out of range", parsed_index)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex_color(line: &str) -> Result<Option<Vec4>, Error> {
+        let splits: Vec<&str> = line.split_whitespace().collect();
+        parse_vertex_color(&splits, 1)
+    }
+
+    #[test]
+    fn vertices_without_color_have_none() {
+        assert_eq!(vertex_color("v 1 2 3"), Ok(None));
+        // Fourth component is w, not a color
+        assert_eq!(vertex_color("v 1 2 3 1"), Ok(None));
+    }
+
+    #[test]
+    fn vertex_color_is_parsed_with_optional_alpha() {
+        assert_eq!(vertex_color("v 1 2 3 0.25 0.5 0.75"), Ok(Some(Vec4::new(0.25, 0.5, 0.75, 1.0))));
+        assert_eq!(vertex_color("v 1 2 3 0.25 0.5 0.75 0.5"), Ok(Some(Vec4::new(0.25, 0.5, 0.75, 0.5))));
+    }
+
+    #[test]
+    fn incomplete_vertex_color_is_rejected() {
+        assert!(matches!(vertex_color("v 1 2 3 0.25 0.5"), Err(Error::AssetDecode { line: Some(1), .. })));
+        assert!(matches!(vertex_color("v 1 2 3 0.25 0.5 0.75 1 1"), Err(Error::AssetDecode { line: Some(1), .. })));
+        assert!(matches!(vertex_color("v 1 2 3 0.25 red 0.75"), Err(Error::AssetDecode { line: Some(1), .. })));
+    }
+
+    #[test]
+    fn vertices_without_color_are_white_when_others_have_it() {
+        let obj = "v 0 0 0 1 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nf 1/1/1 2/1/1 3/1/1\n";
+        let mesh_data = load_obj_data(obj).unwrap();
+
+        let colors = mesh_data.get_colors().unwrap();
+        assert_eq!(colors, &vec![Vec4::new(1.0, 0.0, 0.0, 1.0), Vec4::ONE, Vec4::ONE]);
+    }
+
+    #[test]
+    fn mesh_without_vertex_colors_has_no_color_data() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nf 1/1/1 2/1/1 3/1/1\n";
+        assert!(load_obj_data(obj).unwrap().get_colors().is_none());
+    }
+
+    // Unit quad split into two triangles that share two corners
+    const QUAD: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nf 1/1/1 2/1/1 3/1/1\nf 1/1/1 3/1/1 4/1/1\n";
+
+    #[test]
+    fn shared_face_corners_are_stored_once() {
+        let mesh_data = load_obj_data(QUAD).unwrap();
+
+        assert_eq!(mesh_data.get_vertices().len(), 4);
+        assert_eq!(mesh_data.get_elements_count(), 6);
+        let indices = mesh_data.get_indices().unwrap();
+        let indices: Vec<usize> = (0..indices.len()).map(|idx| indices.get(idx)).collect();
+        assert_eq!(indices, vec![0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn corners_with_different_normals_stay_separate() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nvn 0 0 -1\nf 1/1/1 2/1/1 3/1/1\nf 1/1/2 3/1/2 2/1/2\n";
+        let mesh_data = load_obj_data(obj).unwrap();
+
+        assert_eq!(mesh_data.get_vertices().len(), 6);
+        assert_eq!(mesh_data.get_elements_count(), 6);
+    }
+
+    #[test]
+    fn polygons_are_triangulated_into_one_index_per_corner() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nf 1/1/1 2/1/1 3/1/1 4/1/1\n";
+        let mesh_data = load_obj_data(obj).unwrap();
+
+        assert_eq!(mesh_data.get_vertices().len(), 4);
+        assert_eq!(mesh_data.get_elements_count(), 6);
+    }
+
+    #[test]
+    fn small_meshes_use_16_bit_indices() {
+        let mesh_data = load_obj_data(QUAD).unwrap();
+        assert!(matches!(mesh_data.get_indices(), Some(MeshIndices::U16(_))));
+    }
+
+    #[test]
+    fn meshes_past_16_bit_range_use_32_bit_indices() {
+        let vertices_count = u16::MAX as usize + 3;
+        let mut obj = String::new();
+        for idx in 0..vertices_count {
+            obj.push_str(&format!("v {} 0 0\n", idx));
+        }
+        obj.push_str("vt 0 0\nvn 0 0 1\n");
+        for idx in (1..=vertices_count).step_by(3) {
+            obj.push_str(&format!("f {}/1/1 {}/1/1 {}/1/1\n", idx, idx + 1, idx + 2));
+        }
+
+        let mesh_data = load_obj_data(&obj).unwrap();
+        assert_eq!(mesh_data.get_vertices().len(), vertices_count);
+        assert!(matches!(mesh_data.get_indices(), Some(MeshIndices::U32(_))));
+    }
+
+    #[test]
+    fn malformed_faces_report_their_line() {
+        let header = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\n";
+        for face in ["f 1/1/1 2/1/1", "f 1/1 2/1 3/1", "f 1/1/1 2/1/1 4/1/1", "f 1/1/1 2/1/1 x/1/1"] {
+            let result = load_obj_data(&format!("{}{}\n", header, face));
+            assert!(matches!(result, Err(Error::AssetDecode { line: Some(6), .. })), "{}", face);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use std::cmp::Ordering;
+
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use crate::vertex_data::VertexData;
+
+const DEFAULT_RANDOM_SEED: u32 = 0x9e3779b9;
+
+// Where new particles appear, relative to the emitter position
+#[derive(Copy, Clone, PartialEq)]
+pub enum EmitterShape {
+    Point,
+    // Anywhere inside the sphere
+    Sphere { radius: f32 },
+    // Anywhere inside the box, half of its size along each axis
+    Box { half_extents: Vec3 },
+}
+
+// Texture atlas of equally sized frames, numbered row by row from the top left
+#[derive(Copy, Clone, PartialEq)]
+pub enum ParticleFrames {
+    // Whole texture
+    Single,
+    // Every particle goes through all frames once over its lifetime
+    Animated { columns: u32, rows: u32, frame_count: u32 },
+    // Every particle gets a random frame and keeps it
+    Random { columns: u32, rows: u32, frame_count: u32 },
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct ParticleEmitterSettings {
+    // Particles spawned per second while the emitter is emitting
+    pub rate: f32,
+    // Nothing is spawned while this many particles are alive
+    pub max_particles: usize,
+    pub shape: EmitterShape,
+    // In seconds, picked at random in between for every particle
+    pub lifetime_min: f32,
+    pub lifetime_max: f32,
+    // Every component is picked at random in between, units per second
+    pub velocity_min: Vec3,
+    pub velocity_max: Vec3,
+    // Acceleration of every particle, units per second squared
+    pub gravity: Vec3,
+    // Color and size go from start to end over particle lifetime. Color multiplies texture color.
+    pub start_color: Vec4,
+    pub end_color: Vec4,
+    pub start_size: f32,
+    pub end_size: f32,
+    pub frames: ParticleFrames,
+}
+
+impl ParticleEmitterSettings {
+    pub fn new(rate: f32, lifetime: f32) -> ParticleEmitterSettings {
+        ParticleEmitterSettings {
+            rate,
+            max_particles: 256,
+            shape: EmitterShape::Point,
+            lifetime_min: lifetime,
+            lifetime_max: lifetime,
+            velocity_min: Vec3::ZERO,
+            velocity_max: Vec3::ZERO,
+            gravity: Vec3::ZERO,
+            start_color: Vec4::ONE,
+            end_color: Vec4::ONE,
+            start_size: 1.0,
+            end_size: 1.0,
+            frames: ParticleFrames::Single
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Particle {
+    // World space, particles stay where they are when the emitter moves
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,
+    lifetime: f32,
+    // Used by ParticleFrames::Random
+    frame: u32,
+}
+
+// Particles are simulated on the CPU in update, call it from Scene::on_update, and drawn with
+// Renderer::render_particles as camera facing quads, all of them in one draw.
+pub struct ParticleEmitter {
+    settings: ParticleEmitterSettings,
+    position: Vec3,
+    emitting: bool,
+    particles: Vec<Particle>,
+    // Part of a particle left over from the last update, so that low rates still spawn
+    spawn_accumulator: f32,
+    random_state: u32,
+}
+
+impl ParticleEmitter {
+    pub fn new(settings: ParticleEmitterSettings, position: Vec3) -> ParticleEmitter {
+        ParticleEmitter {
+            settings,
+            position,
+            emitting: true,
+            particles: vec![],
+            spawn_accumulator: 0.0,
+            random_state: DEFAULT_RANDOM_SEED
+        }
+    }
+
+    pub fn get_settings(&self) -> &ParticleEmitterSettings {
+        &self.settings
+    }
+
+    // Particles that are already alive keep their lifetime and velocity
+    pub fn set_settings(&mut self, settings: ParticleEmitterSettings) {
+        self.settings = settings;
+    }
+
+    pub fn get_position(&self) -> &Vec3 {
+        &self.position
+    }
+
+    pub fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+    }
+
+    pub fn is_emitting(&self) -> bool {
+        self.emitting
+    }
+
+    // Stopped emitters still simulate particles that are alive
+    pub fn set_emitting(&mut self, emitting: bool) {
+        self.emitting = emitting;
+        self.spawn_accumulator = 0.0;
+    }
+
+    // Emitters with the same seed and settings spawn the same particles
+    pub fn set_seed(&mut self, seed: u32) {
+        self.random_state = if seed == 0 { DEFAULT_RANDOM_SEED } else { seed };
+    }
+
+    pub fn get_particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    // Spawns particles right away, whether the emitter is emitting or not
+    pub fn burst(&mut self, count: usize) {
+        let count = count.min(self.settings.max_particles.saturating_sub(self.particles.len()));
+        for _ in 0..count {
+            let particle = self.spawn_particle();
+            self.particles.push(particle);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.spawn_accumulator = 0.0;
+    }
+
+    // Delta time in seconds, same as Scene::on_update gets
+    pub fn update(&mut self, delta_time: f64) {
+        let delta_time = delta_time as f32;
+
+        let gravity = self.settings.gravity;
+        for particle in &mut self.particles {
+            particle.age += delta_time;
+            particle.velocity += gravity * delta_time;
+            particle.position += particle.velocity * delta_time;
+        }
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+
+        if self.emitting {
+            self.spawn_accumulator += self.settings.rate.max(0.0) * delta_time;
+            let count = self.spawn_accumulator.floor();
+            self.spawn_accumulator -= count;
+            self.burst(count as usize);
+        }
+    }
+
+    fn spawn_particle(&mut self) -> Particle {
+        let offset = match self.settings.shape {
+            EmitterShape::Point => Vec3::ZERO,
+            EmitterShape::Sphere { radius } => self.random_in_unit_sphere() * radius,
+            EmitterShape::Box { half_extents } => self.random_vec3(-half_extents, half_extents),
+        };
+        let velocity = self.random_vec3(self.settings.velocity_min, self.settings.velocity_max);
+        let lifetime = self.random_range(self.settings.lifetime_min, self.settings.lifetime_max);
+        let frame = match self.settings.frames {
+            ParticleFrames::Random { frame_count, .. } => (self.random_range(0.0, frame_count as f32) as u32).min(frame_count.saturating_sub(1)),
+            _ => 0,
+        };
+
+        Particle { position: self.position + offset, velocity, age: 0.0, lifetime, frame }
+    }
+
+    // Appends 6 vertices and their colors per particle, relative to the emitter position. Translucent particles
+    // are sorted back to front.
+    pub(crate) fn build_vertices(&self, view_matrix: &Mat4, sort_back_to_front: bool, vertex_data: &mut Vec<VertexData>, colors: &mut Vec<Vec4>) {
+        let camera_matrix = view_matrix.inverse();
+        let camera_right = camera_matrix.x_axis.truncate().normalize_or_zero();
+        let camera_up = camera_matrix.y_axis.truncate().normalize_or_zero();
+        let normal = camera_right.cross(camera_up);
+
+        let mut particles: Vec<&Particle> = self.particles.iter().collect();
+        if sort_back_to_front {
+            // Camera looks down -z, smaller view space z is farther away
+            let depth = |particle: &Particle| view_matrix.transform_point3(particle.position).z;
+            particles.sort_by(|a, b| depth(a).partial_cmp(&depth(b)).unwrap_or(Ordering::Equal));
+        }
+
+        vertex_data.reserve(particles.len() * 6);
+        colors.reserve(particles.len() * 6);
+        for particle in particles {
+            let life = if particle.lifetime > 0.0 { (particle.age / particle.lifetime).clamp(0.0, 1.0) } else { 1.0 };
+            let color = self.settings.start_color.lerp(self.settings.end_color, life);
+            let half_size = (self.settings.start_size + (self.settings.end_size - self.settings.start_size) * life) / 2.0;
+            let (uv_min, uv_max) = self.get_frame_texture_coords(particle, life);
+
+            let center = particle.position - self.position;
+            let right = camera_right * half_size;
+            let up = camera_up * half_size;
+            let corners = [
+                (center - right - up, uv_min),
+                (center + right - up, Vec2::new(uv_max.x, uv_min.y)),
+                (center + right + up, uv_max),
+                (center - right + up, Vec2::new(uv_min.x, uv_max.y)),
+            ];
+            for index in [0, 1, 2, 0, 2, 3] {
+                let (position, texture_coords) = corners[index];
+                vertex_data.push(VertexData::new(position, texture_coords, normal));
+                colors.push(color);
+            }
+        }
+    }
+
+    // Bottom left and top right texture coordinates of the particle frame
+    fn get_frame_texture_coords(&self, particle: &Particle, life: f32) -> (Vec2, Vec2) {
+        let (columns, rows, frame) = match self.settings.frames {
+            ParticleFrames::Single => return (Vec2::ZERO, Vec2::ONE),
+            ParticleFrames::Animated { columns, rows, frame_count } => {
+                let frame = ((life * frame_count as f32) as u32).min(frame_count.saturating_sub(1));
+                (columns, rows, frame)
+            }
+            ParticleFrames::Random { columns, rows, .. } => (columns, rows, particle.frame),
+        };
+        let columns = columns.max(1);
+        let rows = rows.max(1);
+
+        // Image rows are stored bottom up, frames are numbered from the top
+        let cell_size = Vec2::new(1.0 / columns as f32, 1.0 / rows as f32);
+        let column = (frame % columns) as f32;
+        let row = (frame / columns) as f32;
+        let uv_min = Vec2::new(column * cell_size.x, 1.0 - (row + 1.0) * cell_size.y);
+        (uv_min, uv_min + cell_size)
+    }
+
+    // Xorshift, good enough for effects and keeps emitters deterministic
+    fn random(&mut self) -> f32 {
+        let mut state = self.random_state;
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        self.random_state = state;
+        (state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn random_range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.random()
+    }
+
+    fn random_vec3(&mut self, min: Vec3, max: Vec3) -> Vec3 {
+        Vec3::new(self.random_range(min.x, max.x), self.random_range(min.y, max.y), self.random_range(min.z, max.z))
+    }
+
+    fn random_in_unit_sphere(&mut self) -> Vec3 {
+        loop {
+            let point = self.random_vec3(Vec3::NEG_ONE, Vec3::ONE);
+            if point.length_squared() <= 1.0 {
+                return point;
+            }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+// Custom full-screen passes run after the scene is drawn and color depth is reduced, in ascending order.
+// Their fragment shader gets result of the previous pass and draws the next one:
+//
+//     #version 450 core
+//     in vec2 frag_texture_coords;
+//     out vec4 color;
+//     layout(binding = 0) uniform sampler2D texture_sampler;
+//     uniform vec2 framebuffer_size; // optional, set by the engine
+//
+// Other uniforms are set by name with Renderer::set_post_process_uniform.
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PostProcessPassId(pub(crate) usize);
@@ -0,0 +1,71 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::internal::framebuffer::Framebuffer;
+use crate::texture::Texture;
+
+// Offscreen image the 3D scene can be drawn into, for mirrors, security camera monitors and portals.
+// Draw into it between Renderer::begin_render_target and end_render_target, then use its texture in a
+// material like any other.
+pub struct RenderTarget {
+    // Shared with the renderer until the frame that draws into it is done
+    framebuffer: Rc<Framebuffer>,
+}
+
+impl RenderTarget {
+    pub fn new(width: u32, height: u32) -> Result<RenderTarget, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::InvalidArgument(format!("Render target can't be {}x{}", width, height)));
+        }
+        Ok(RenderTarget { framebuffer: Rc::new(Framebuffer::new(width as i32, height as i32)?) })
+    }
+
+    // Color of what was drawn into the target, rows from bottom to top like textures loaded from images
+    pub fn get_texture(&self) -> &Rc<Texture> {
+        self.framebuffer.get_texture()
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.framebuffer.get_width() as u32
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.framebuffer.get_height() as u32
+    }
+
+    pub(crate) fn get_framebuffer(&self) -> &Rc<Framebuffer> {
+        &self.framebuffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_sized_render_target_is_an_invalid_argument() {
+        for (width, height) in [(0, 64), (64, 0)] {
+            assert!(matches!(RenderTarget::new(width, height), Err(Error::InvalidArgument(_))));
+        }
+    }
+}
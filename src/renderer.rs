@@ -17,30 +17,170 @@
 // along with RSFX. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use glam::{Mat4, Vec3};
+use std::rc::Rc;
 
-use crate::internal::gl_renderer::GlRenderer;
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use crate::background::{BackgroundLayer, Skybox};
+use crate::bitmap_font::{BitmapFont, TextStyle};
+use crate::error::Error;
+use crate::graphics_settings::GraphicsSettings;
+use crate::headless::RecordedFrame;
+use crate::internal::background_renderer::{BackgroundLayerDraw, SkyboxDraw};
+use crate::internal::framebuffer::Framebuffer;
+use crate::internal::overlay_sprite::OverlaySprite;
+use crate::internal::render_backend::RenderBackend;
+use crate::internal::render_target_scope::RenderTargetScope;
 use crate::internal::renderable::Renderable;
 use crate::internal::renderer_command::RendererCommand;
+use crate::light::{Light, LightId, LightKind};
+use crate::material::{Material, MaterialShader};
 use crate::mesh::Mesh;
+use crate::particles::ParticleEmitter;
+use crate::post_process::PostProcessPassId;
+use crate::render_target::RenderTarget;
+use crate::screenshot::{Screenshot, ScreenshotSource};
+use crate::sprite::Sprite;
 use crate::texture::Texture;
+use crate::uniform_value::UniformValue;
+use crate::vertex_data::VertexData;
 
-const UNIFORM_TRANSFORMATION_MATRIX_LOCATION: i32 = 3;
-const UNIFORM_PROJECTION_MATRIX_LOCATION: i32 = 7;
-const UNIFORM_VIEW_MATRIX_LOCATION: i32 = 11;
+pub(crate) const UNIFORM_TRANSFORMATION_MATRIX_LOCATION: i32 = 3;
+pub(crate) const UNIFORM_PROJECTION_MATRIX_LOCATION: i32 = 7;
+pub(crate) const UNIFORM_VIEW_MATRIX_LOCATION: i32 = 11;
 const UNIFORM_FOG_MIN_LOCATION: i32 = 15;
 const UNIFORM_FOG_MAX_LOCATION: i32 = 16;
+const UNIFORM_FOG_COLOR_LOCATION: i32 = 42;
+const UNIFORM_FOG_MODE_LOCATION: i32 = 43;
+const UNIFORM_FOG_DENSITY_LOCATION: i32 = 44;
+const UNIFORM_FOG_HEIGHT_TOP_LOCATION: i32 = 45;
+const UNIFORM_FOG_HEIGHT_THICKNESS_LOCATION: i32 = 46;
 const UNIFORM_DIRECTIONAL_LIGT_COLOR_LOCATION: i32 = 17;
 const UNIFORM_DIRECTIONAL_LIGT_DIRECTION_LOCATION: i32 = 18;
 const UNIFORM_DIRECTIONAL_LIGT_BRIGHTNESS_LOCATION: i32 = 19;
+// Light arrays take one location per element
+const UNIFORM_LIGHT_COUNT_LOCATION: i32 = 20;
+const UNIFORM_LIGHT_POSITIONS_LOCATION: i32 = 21;
+const UNIFORM_LIGHT_COLORS_LOCATION: i32 = 25;
+const UNIFORM_LIGHT_DIRECTIONS_LOCATION: i32 = 29;
+const UNIFORM_LIGHT_PARAMETERS_LOCATION: i32 = 33;
+const UNIFORM_TEXTURE_MAPPING_LOCATION: i32 = 37;
+const UNIFORM_AFFINE_SUBDIVISION_EDGE_LENGTH_LOCATION: i32 = 38;
+
+pub const MAX_LIGHTS: usize = 64;
+// Has to match array sizes in main pass vertex shader
+pub const MAX_LIGHTS_PER_RENDERABLE: usize = 4;
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum BlendMode {
+    // Drawn first, writes depth
+    Opaque,
+    // Classic alpha blending using texture alpha
+    Alpha,
+    // Background + foreground, for fire, sparks and glows
+    Additive,
+    // Background - foreground, PSX style darkening
+    Subtractive,
+    // 50% background + 50% foreground, PSX style semi-transparency that ignores texture alpha
+    Average,
+}
+
+impl BlendMode {
+    // Everything but Opaque is drawn back to front after opaque renderables, without writing depth
+    pub fn is_translucent(&self) -> bool {
+        *self != BlendMode::Opaque
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum TextureMapping {
+    PerspectiveCorrect,
+    // UVs interpolated in screen space like on PSX, textures warp on triangles seen at an angle
+    Affine,
+}
+
+impl TextureMapping {
+    fn uniform_value(&self) -> i32 {
+        match self {
+            TextureMapping::PerspectiveCorrect => 0,
+            TextureMapping::Affine => 1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum FogMode {
+    Off,
+    // Fades in between minimum and maximum fog distance
+    Linear,
+    // Starts at minimum fog distance, maximum distance is not used
+    Exponential { density: f32 },
+    ExponentialSquared { density: f32 },
+}
+
+// Fog that only covers what is low enough, like mist over a lake. Full below top - thickness, fading out
+// towards top, in world units.
+#[derive(Copy, Clone, PartialEq)]
+pub struct HeightFog {
+    pub top: f32,
+    pub thickness: f32,
+}
+
+// Renderer settings of the screen, put back when a render target ends
+struct ScreenSettings {
+    transformation_matrix: Mat4,
+    view_matrix: Mat4,
+    texture_mapping: TextureMapping,
+    texture_mapping_override: Option<TextureMapping>,
+    affine_subdivision_edge_length: f32,
+    lights: Vec<Option<Light>>,
+    skybox: Option<Skybox>,
+}
 
 pub struct Renderer {
-    gl_renderer: GlRenderer,
+    backend: RenderBackend,
     commands: Vec<RendererCommand>,
+    instance_transforms: Vec<Mat4>,
+    // Particle quads of the frame, every RenderParticles draws its own range
+    particle_vertices: Vec<VertexData>,
+    particle_colors: Vec<Vec4>,
+    // Textures and render targets commands of this frame refer to by id, kept alive until the passes ran
+    frame_textures: Vec<Rc<Texture>>,
+    frame_framebuffers: Vec<Rc<Framebuffer>>,
+    lights: Vec<Option<Light>>,
+    // Last transformation matrix, lights are picked by distance to its translation
+    transformation_matrix: Mat4,
+    // Last view matrix, particles are turned to face it
+    view_matrix: Mat4,
+    // Set while a render target is drawn
+    screen_settings: RenderTargetScope<ScreenSettings>,
+    texture_mapping: TextureMapping,
+    texture_mapping_override: Option<TextureMapping>,
+    // Longest edge in framebuffer pixels before affine mapped triangles get split, zero when off
+    affine_subdivision_edge_length: f32,
+    skybox: Option<Skybox>,
+    next_post_process_pass_id: usize,
 }
 impl Renderer {
-    pub fn new(gl_renderer: GlRenderer) -> Renderer {
-        Renderer { gl_renderer, commands: vec![] }
+    pub fn new(backend: RenderBackend) -> Renderer {
+        Renderer {
+            backend,
+            commands: vec![],
+            instance_transforms: vec![],
+            particle_vertices: vec![],
+            particle_colors: vec![],
+            frame_textures: vec![],
+            frame_framebuffers: vec![],
+            lights: vec![None; MAX_LIGHTS],
+            transformation_matrix: Mat4::IDENTITY,
+            view_matrix: Mat4::IDENTITY,
+            screen_settings: RenderTargetScope::new(),
+            texture_mapping: TextureMapping::PerspectiveCorrect,
+            texture_mapping_override: None,
+            affine_subdivision_edge_length: 0.0,
+            skybox: None,
+            next_post_process_pass_id: 0
+        }
     }
     
     pub fn clear_screen(&mut self) {
@@ -52,6 +192,7 @@ impl Renderer {
     }
     
     pub fn set_transformation_matrix(&mut self, matrix: &Mat4) {
+        self.transformation_matrix = *matrix;
         self.commands.push(RendererCommand::SetUniformMat4(UNIFORM_TRANSFORMATION_MATRIX_LOCATION, matrix.clone()));
     }
     
@@ -60,6 +201,7 @@ impl Renderer {
     }
     
     pub fn set_view_matrix(&mut self, matrix: &Mat4) {
+        self.view_matrix = *matrix;
         self.commands.push(RendererCommand::SetUniformMat4(UNIFORM_VIEW_MATRIX_LOCATION, matrix.clone()));
     }
     
@@ -79,6 +221,33 @@ impl Renderer {
         self.commands.push(RendererCommand::SetUniformFloat(UNIFORM_FOG_MAX_LOCATION, value));
     }
     
+    // Linear fog is used until set otherwise
+    pub fn set_fog_mode(&mut self, fog_mode: FogMode) {
+        let (mode, density) = match fog_mode {
+            FogMode::Linear => (0, 0.0),
+            FogMode::Exponential { density } => (1, density),
+            FogMode::ExponentialSquared { density } => (2, density),
+            FogMode::Off => (3, 0.0),
+        };
+        self.commands.push(RendererCommand::SetUniformInt(UNIFORM_FOG_MODE_LOCATION, mode));
+        self.commands.push(RendererCommand::SetUniformFloat(UNIFORM_FOG_DENSITY_LOCATION, density.max(0.0)));
+    }
+
+    // Components in 0..1 range, unlike set_clear_color. Black until set.
+    pub fn set_fog_color(&mut self, color: &Vec3) {
+        self.commands.push(RendererCommand::SetUniformVec3(UNIFORM_FOG_COLOR_LOCATION, *color));
+    }
+
+    // Height fog multiplies distance fog, so far away things low enough get foggy
+    pub fn set_height_fog(&mut self, height_fog: Option<HeightFog>) {
+        let (top, thickness) = match height_fog {
+            Some(height_fog) => (height_fog.top, height_fog.thickness.max(0.0001)),
+            None => (0.0, 0.0)
+        };
+        self.commands.push(RendererCommand::SetUniformFloat(UNIFORM_FOG_HEIGHT_TOP_LOCATION, top));
+        self.commands.push(RendererCommand::SetUniformFloat(UNIFORM_FOG_HEIGHT_THICKNESS_LOCATION, thickness));
+    }
+
     pub fn set_direction_light_color(&mut self, color: &Vec3) {
         self.commands.push(RendererCommand::SetUniformVec3(UNIFORM_DIRECTIONAL_LIGT_COLOR_LOCATION, color.clone()));
     }
@@ -92,14 +261,299 @@ impl Renderer {
         self.commands.push(RendererCommand::SetUniformFloat(UNIFORM_DIRECTIONAL_LIGT_BRIGHTNESS_LOCATION, value));
     }
 
-    pub fn render(&mut self, mesh: &Mesh, texture: &Texture) {
-        self.commands.push(RendererCommand::Render(Renderable::new(mesh, texture)));
+    // Used for every renderable unless overridden
+    pub fn set_texture_mapping(&mut self, texture_mapping: TextureMapping) {
+        self.texture_mapping = texture_mapping;
+    }
+
+    // Applies to renderables submitted after it, None goes back to the one set with set_texture_mapping
+    pub fn set_texture_mapping_override(&mut self, texture_mapping: Option<TextureMapping>) {
+        self.texture_mapping_override = texture_mapping;
+    }
+
+    // Affine mapped triangles with an edge longer than given amount of framebuffer pixels get split into
+    // smaller ones to limit warping, longer than double get split further. None turns subdivision off.
+    // Used for every renderable submitted after it.
+    pub fn set_affine_subdivision(&mut self, max_edge_length: Option<f32>) {
+        self.affine_subdivision_edge_length = max_edge_length.map(|length| length.max(1.0)).unwrap_or(0.0);
+    }
+
+    // Lights stay until removed, move them with set_light
+    pub fn add_light(&mut self, light: &Light) -> Result<LightId, Error> {
+        match self.lights.iter().position(|slot| slot.is_none()) {
+            Some(index) => {
+                self.lights[index] = Some(*light);
+                Ok(LightId(index))
+            }
+            None => Err(Error::LimitReached(format!("no more than {} lights can be added", MAX_LIGHTS)))
+        }
+    }
+
+    pub fn set_light(&mut self, light_id: LightId, light: &Light) {
+        if let Some(slot) = self.lights.get_mut(light_id.0) {
+            if slot.is_some() {
+                *slot = Some(*light);
+            }
+        }
+    }
+
+    pub fn remove_light(&mut self, light_id: LightId) {
+        if let Some(slot) = self.lights.get_mut(light_id.0) {
+            *slot = None;
+        }
+    }
+
+    pub fn clear_lights(&mut self) {
+        self.lights.iter_mut().for_each(|slot| *slot = None);
+    }
+
+    pub fn render(&mut self, mesh: &Mesh, material: &Material) {
+        self.push_renderable_state(material);
+        self.commands.push(RendererCommand::Render(Renderable::new(mesh, material)));
+    }
+
+    // Vertex shader is the built-in one when not given. Fails in headless runs, there is nothing to compile
+    // shaders with.
+    pub fn create_material_shader(&mut self, vertex_shader_source: Option<&str>, fragment_shader_source: &str) -> Result<MaterialShader, Error> {
+        self.backend.create_material_shader(vertex_shader_source, fragment_shader_source)
+    }
+
+    // See post_process module for what the fragment shader gets. Passes start enabled and run in ascending
+    // order, passes with the same order run in the order they were added.
+    pub fn add_post_process_pass(&mut self, fragment_shader_source: &str, order: i32) -> Result<PostProcessPassId, Error> {
+        let id = PostProcessPassId(self.next_post_process_pass_id);
+        self.backend.add_post_process_pass(id, fragment_shader_source, order)?;
+        self.next_post_process_pass_id += 1;
+        Ok(id)
+    }
+
+    pub fn remove_post_process_pass(&mut self, id: PostProcessPassId) {
+        self.backend.remove_post_process_pass(id);
+    }
+
+    pub fn set_post_process_pass_enabled(&mut self, id: PostProcessPassId, enabled: bool) {
+        self.backend.set_post_process_pass_enabled(id, enabled);
+    }
+
+    pub fn set_post_process_pass_order(&mut self, id: PostProcessPassId, order: i32) {
+        self.backend.set_post_process_pass_order(id, order);
+    }
+
+    // Takes effect right away and stays until changed, uniforms the shader doesn't use are ignored
+    pub fn set_post_process_uniform(&mut self, id: PostProcessPassId, name: &str, value: UniformValue) {
+        self.backend.set_post_process_uniform(id, name, &value);
+    }
+
+    // Captures the last rendered frame, not the one currently being submitted
+    pub fn capture_screenshot(&self, source: ScreenshotSource) -> Result<Screenshot, Error> {
+        self.backend.capture_screenshot(source)
+    }
+
+    // Draws the mesh once per transform with a single draw call. Every transform is applied on top of
+    // the current transformation matrix, set it to identity to place instances in world space.
+    pub fn render_instanced(&mut self, mesh: &Mesh, material: &Material, transforms: &[Mat4]) {
+        if transforms.is_empty() {
+            return;
+        }
+
+        // Instances share lights picked for the parent transform
+        self.push_renderable_state(material);
+        let first_instance = self.instance_transforms.len() as u32;
+        self.instance_transforms.extend_from_slice(transforms);
+        self.commands.push(RendererCommand::RenderInstanced(Renderable::new(mesh, material), first_instance, transforms.len() as u32));
+    }
+
+    // Everything until end_render_target is drawn into the target instead of the screen. Camera matrices, fog,
+    // clear color, lights, skybox and other settings set in between only apply to the target, the screen keeps
+    // its own. Ids of lights added to the target are no longer valid after it ends.
+    // Targets are drawn in submission order, draw them before whatever shows their texture, otherwise it
+    // shows the previous frame. Sprites, text and post-processing are screen only. Begins end the target
+    // that was drawn before.
+    pub fn begin_render_target(&mut self, render_target: &RenderTarget) {
+        self.end_render_target();
+        self.screen_settings.begin(|| ScreenSettings {
+            transformation_matrix: self.transformation_matrix,
+            view_matrix: self.view_matrix,
+            texture_mapping: self.texture_mapping,
+            texture_mapping_override: self.texture_mapping_override,
+            affine_subdivision_edge_length: self.affine_subdivision_edge_length,
+            lights: self.lights.clone(),
+            skybox: self.skybox.clone()
+        });
+
+        let framebuffer = render_target.get_framebuffer();
+        self.frame_framebuffers.push(framebuffer.clone());
+        self.commands.push(RendererCommand::BeginRenderTarget(framebuffer.get_binding()));
+    }
+
+    pub fn end_render_target(&mut self) {
+        if let Some(screen_settings) = self.screen_settings.end() {
+            self.transformation_matrix = screen_settings.transformation_matrix;
+            self.view_matrix = screen_settings.view_matrix;
+            self.texture_mapping = screen_settings.texture_mapping;
+            self.texture_mapping_override = screen_settings.texture_mapping_override;
+            self.affine_subdivision_edge_length = screen_settings.affine_subdivision_edge_length;
+            self.lights = screen_settings.lights;
+            self.skybox = screen_settings.skybox;
+            self.commands.push(RendererCommand::EndRenderTarget());
+        }
+    }
+
+    // Sky is drawn after every clear_screen, before anything else. It follows camera rotation from the view
+    // matrix, but not its position, and has no fog or vertex snapping. Stays until changed.
+    pub fn set_skybox(&mut self, skybox: Option<&Skybox>) {
+        if let Some(skybox) = skybox {
+            self.frame_textures.extend(skybox.get_textures().iter().cloned());
+        }
+        self.skybox = skybox.cloned();
+        self.commands.push(RendererCommand::SetSkybox(skybox.map(SkyboxDraw::new)));
+    }
+
+    // Drawn behind the 3D scene of this frame, over the skybox, in the order layers were drawn
+    pub fn draw_background_layer(&mut self, layer: &BackgroundLayer) {
+        self.frame_textures.push(layer.get_texture().clone());
+        self.commands.push(RendererCommand::DrawBackgroundLayer(BackgroundLayerDraw::new(layer)));
+    }
+
+    // Particles face the camera of the current view matrix and are drawn in one draw call with the material.
+    // Transformation matrix is kept, particles are placed in world space.
+    // Vertices are built right away, an emitter can be drawn several times in a frame, e.g. into a render target
+    // and to the screen.
+    pub fn render_particles(&mut self, emitter: &ParticleEmitter, material: &Material) {
+        if emitter.get_particle_count() == 0 {
+            return;
+        }
+
+        let first_vertex = self.particle_vertices.len();
+        emitter.build_vertices(&self.view_matrix, material.get_blend_mode().is_translucent(), &mut self.particle_vertices, &mut self.particle_colors);
+        let vertices_count = (self.particle_vertices.len() - first_vertex) as i32;
+
+        // Vertices are relative to the emitter, lights and draw order of translucent emitters go by its position
+        let transformation_matrix = self.transformation_matrix;
+        self.set_transformation_matrix(&Mat4::from_translation(*emitter.get_position()));
+        self.push_renderable_state(material);
+        self.commands.push(RendererCommand::RenderParticles(Renderable::without_mesh(material, vertices_count), first_vertex as i32));
+        self.set_transformation_matrix(&transformation_matrix);
+    }
+
+    // Sprites are drawn over the 3D scene once it's done, see Sprite for the coordinates. Render targets don't
+    // get sprites, they are skipped while one is being drawn.
+    pub fn draw_sprite(&mut self, texture: &Texture, sprite: &Sprite) {
+        if self.screen_settings.is_open() {
+            return;
+        }
+        self.commands.push(RendererCommand::DrawSprite(OverlaySprite::new(sprite, Some(texture))));
+    }
+
+    // Sprite filled with its tint, source rect is not used
+    pub fn draw_quad(&mut self, sprite: &Sprite) {
+        if self.screen_settings.is_open() {
+            return;
+        }
+        self.commands.push(RendererCommand::DrawSprite(OverlaySprite::new(sprite, None)));
+    }
+
+    // Position is the top of the first line, at its left edge, center or right edge depending on alignment,
+    // in the same coordinates as sprites. Glyphs are drawn as sprites at whole framebuffer pixels.
+    pub fn draw_text(&mut self, font: &BitmapFont, text: &str, position: Vec2, style: &TextStyle) {
+        self.draw_text_with_offsets(font, text, position, style, |_, _| Vec2::ZERO);
+    }
+
+    // Every character is moved by the offset returned for its index in the text and the character itself,
+    // for shaky or wavy text. Offsets are rounded to whole pixels.
+    pub fn draw_text_with_offsets<F>(&mut self, font: &BitmapFont, text: &str, position: Vec2, style: &TextStyle, offsets: F)
+        where F: Fn(usize, char) -> Vec2
+    {
+        let position = position.round();
+        for glyph in font.layout_text(text, style.alignment, style.wrap_width) {
+            let top_left = position + glyph.position + offsets(glyph.index, glyph.character).round();
+            let size = Vec2::new(glyph.source_rect.width, glyph.source_rect.height);
+
+            let mut sprite = Sprite::new(top_left + Vec2::new(size.x, -size.y) / 2.0, size);
+            sprite.source_rect = Some(glyph.source_rect);
+            sprite.tint = style.color;
+            sprite.layer = style.layer;
+            self.draw_sprite(font.get_texture(), &sprite);
+        }
+    }
+
+    fn push_renderable_state(&mut self, material: &Material) {
+        let texture_mapping = self.texture_mapping_override.unwrap_or(self.texture_mapping);
+        self.commands.push(RendererCommand::SetUniformInt(UNIFORM_TEXTURE_MAPPING_LOCATION, texture_mapping.uniform_value()));
+        self.commands.push(RendererCommand::SetUniformFloat(UNIFORM_AFFINE_SUBDIVISION_EDGE_LENGTH_LOCATION, self.affine_subdivision_edge_length));
+        self.push_nearest_lights();
+
+        for (location, value) in material.get_uniforms() {
+            self.commands.push(RendererCommand::SetMaterialUniform(location, value));
+        }
+    }
+
+    fn push_nearest_lights(&mut self) {
+        let renderable_position = self.transformation_matrix.w_axis.truncate();
+        let mut nearest: Vec<&Light> = self.lights.iter().flatten().collect();
+        if nearest.is_empty() {
+            self.commands.push(RendererCommand::SetUniformInt(UNIFORM_LIGHT_COUNT_LOCATION, 0));
+            return;
+        }
+        nearest.sort_by(|a, b| {
+            let a_distance = a.get_position().distance_squared(renderable_position);
+            let b_distance = b.get_position().distance_squared(renderable_position);
+            a_distance.total_cmp(&b_distance)
+        });
+        nearest.truncate(MAX_LIGHTS_PER_RENDERABLE);
+
+        self.commands.push(RendererCommand::SetUniformInt(UNIFORM_LIGHT_COUNT_LOCATION, nearest.len() as i32));
+        // Unused slots are set too, so every light uniform is known from the first draw on and command
+        // sorting doesn't have to split the frame
+        for slot in 0..MAX_LIGHTS_PER_RENDERABLE {
+            let (position, color, direction, parameters) = match nearest.get(slot) {
+                Some(light) => {
+                    // Parameters are range, falloff and cosines of outer and inner spot angles. Point lights
+                    // use cosine of -1, which accepts every direction.
+                    let (direction, outer_cos, inner_cos) = match light.get_kind() {
+                        LightKind::Point => (Vec3::ZERO, -1.0, -1.0),
+                        LightKind::Spot { direction, inner_angle, outer_angle } => (*direction, outer_angle.cos(), inner_angle.cos()),
+                    };
+                    let parameters = Vec4::new(light.get_range(), light.get_falloff(), outer_cos, inner_cos);
+                    (*light.get_position(), *light.get_color(), direction, parameters)
+                }
+                None => (Vec3::ZERO, Vec3::ZERO, Vec3::ZERO, Vec4::ZERO)
+            };
+            let slot = slot as i32;
+            self.commands.push(RendererCommand::SetUniformVec3(UNIFORM_LIGHT_POSITIONS_LOCATION + slot, position));
+            self.commands.push(RendererCommand::SetUniformVec3(UNIFORM_LIGHT_COLORS_LOCATION + slot, color));
+            self.commands.push(RendererCommand::SetUniformVec3(UNIFORM_LIGHT_DIRECTIONS_LOCATION + slot, direction));
+            self.commands.push(RendererCommand::SetUniformVec4(UNIFORM_LIGHT_PARAMETERS_LOCATION + slot, parameters));
+        }
     }
 
     pub(crate) fn run_passes(&mut self) {
-        self.gl_renderer.run_passes(&self.commands);
+        // Scene may leave a target open, next frame has to start on the screen
+        self.end_render_target();
+        self.backend.run_passes(&self.commands, &self.instance_transforms, &self.particle_vertices, &self.particle_colors);
 
         // And reset renderables
         self.commands.clear();
+        self.instance_transforms.clear();
+        self.particle_vertices.clear();
+        self.particle_colors.clear();
+        self.frame_textures.clear();
+        self.frame_framebuffers.clear();
+    }
+
+    pub(crate) fn resize(&mut self, framebuffer_width: i32, framebuffer_height: i32, window_width: i32, window_height: i32) -> Result<(), Error> {
+        self.backend.resize(framebuffer_width, framebuffer_height, window_width, window_height)
+    }
+
+    // Render pass related settings, resolution changes go through resize
+    pub(crate) fn set_graphics_settings(&mut self, graphics_settings: &GraphicsSettings) {
+        self.backend.set_graphics_settings(graphics_settings);
+    }
+
+    pub(crate) fn take_recorded_frames(&mut self) -> Vec<RecordedFrame> {
+        match &mut self.backend {
+            RenderBackend::Recording(frames) => std::mem::take(frames),
+            _ => vec![]
+        }
     }
 }
\ No newline at end of file
@@ -21,12 +21,385 @@ use crate::input::Input;
 use crate::renderer::Renderer;
 use crate::game_status::GameStatus;
 
+pub enum SceneTransition {
+    // Puts the scene on top of the current one, the current scene is paused but kept alive
+    Push(Box<dyn Scene>),
+    // Destroys the current scene and resumes the one below it
+    Pop,
+    // Destroys the current scene and puts the given one in its place
+    Replace(Box<dyn Scene>),
+    // Destroys every scene on the stack and starts the given one
+    ClearTo(Box<dyn Scene>),
+}
+
 pub trait Scene {
     fn on_start(&mut self, renderer: &mut Renderer);
 
-    fn on_update(&mut self, game_status: &mut GameStatus, renderer: &mut Renderer, input: &Input, delta_time: f64) -> Option<Box<dyn Scene>>;
+    // Only transitions returned by the top scene are applied
+    fn on_update(&mut self, game_status: &mut GameStatus, renderer: &mut Renderer, input: &Input, delta_time: f64) -> Option<SceneTransition>;
     
-    fn on_render(&mut self, renderer: &mut Renderer);
+    // alpha is in [0, 1) range and tells how far the frame is between the last update and the next one
+    fn on_render(&mut self, renderer: &mut Renderer, alpha: f64);
 
     fn on_destroy(&mut self);
+
+    // Called when another scene is pushed on top of this one
+    fn on_pause(&mut self) { }
+
+    // Called when this scene becomes the top one again
+    fn on_resume(&mut self) { }
+
+    // Whether scenes below this one are still rendered, for overlays that don't cover the whole screen
+    fn renders_scenes_below(&self) -> bool {
+        false
+    }
+
+    // Whether scenes below this one are still updated. They don't receive any input.
+    fn updates_scenes_below(&self) -> bool {
+        false
+    }
+}
+
+pub(crate) struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+}
+
+impl SceneStack {
+    pub fn new(mut starting_scene: Box<dyn Scene>, renderer: &mut Renderer) -> SceneStack {
+        starting_scene.on_start(renderer);
+        SceneStack { scenes: vec![starting_scene] }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    pub fn update(&mut self, game_status: &mut GameStatus, renderer: &mut Renderer, input: &Input, delta_time: f64) {
+        if self.scenes.is_empty() {
+            return;
+        }
+
+        let top_idx = self.scenes.len() - 1;
+        let lowest_idx = self.lowest_index(|scene| scene.updates_scenes_below());
+
+        if lowest_idx < top_idx {
+            let idle_input = input.to_idle();
+            for scene in &mut self.scenes[lowest_idx..top_idx] {
+                scene.on_update(game_status, renderer, &idle_input, delta_time);
+            }
+        }
+
+        if let Some(transition) = self.scenes[top_idx].on_update(game_status, renderer, input, delta_time) {
+            self.apply_transition(transition, renderer);
+        }
+    }
+
+    pub fn render(&mut self, renderer: &mut Renderer, alpha: f64) {
+        if self.scenes.is_empty() {
+            return;
+        }
+
+        let lowest_idx = self.lowest_index(|scene| scene.renders_scenes_below());
+        for scene in &mut self.scenes[lowest_idx..] {
+            scene.on_render(renderer, alpha);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        while let Some(mut scene) = self.scenes.pop() {
+            scene.on_destroy();
+        }
+    }
+
+    fn apply_transition(&mut self, transition: SceneTransition, renderer: &mut Renderer) {
+        match transition {
+            SceneTransition::Push(mut scene) => {
+                if let Some(top) = self.scenes.last_mut() {
+                    top.on_pause();
+                }
+                scene.on_start(renderer);
+                self.scenes.push(scene);
+            }
+            SceneTransition::Pop => {
+                if let Some(mut top) = self.scenes.pop() {
+                    top.on_destroy();
+                }
+                if let Some(top) = self.scenes.last_mut() {
+                    top.on_resume();
+                }
+            }
+            SceneTransition::Replace(mut scene) => {
+                if let Some(mut top) = self.scenes.pop() {
+                    top.on_destroy();
+                }
+                scene.on_start(renderer);
+                self.scenes.push(scene);
+            }
+            SceneTransition::ClearTo(mut scene) => {
+                self.clear();
+                scene.on_start(renderer);
+                self.scenes.push(scene);
+            }
+        }
+    }
+
+    // Walks down from the top scene for as long as scenes let the ones below them through
+    fn lowest_index(&self, lets_through: fn(&dyn Scene) -> bool) -> usize {
+        let mut idx = self.scenes.len() - 1;
+        while idx > 0 && lets_through(self.scenes[idx].as_ref()) {
+            idx -= 1;
+        }
+        idx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::input::{Key, State};
+    use crate::internal::render_backend::RenderBackend;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    // Logs every hook call by name and returns the given transitions from its updates, one per update
+    struct TestScene {
+        name: &'static str,
+        log: Log,
+        renders_below: bool,
+        updates_below: bool,
+        transitions: VecDeque<SceneTransition>,
+    }
+
+    impl TestScene {
+        fn new(name: &'static str, log: &Log) -> TestScene {
+            TestScene { name, log: log.clone(), renders_below: false, updates_below: false, transitions: VecDeque::new() }
+        }
+
+        fn transition(mut self, transition: SceneTransition) -> TestScene {
+            self.transitions.push_back(transition);
+            self
+        }
+
+        fn overlay(mut self, renders_below: bool, updates_below: bool) -> TestScene {
+            self.renders_below = renders_below;
+            self.updates_below = updates_below;
+            self
+        }
+
+        fn boxed(self) -> Box<dyn Scene> {
+            Box::new(self)
+        }
+
+        fn log(&self, hook: &str) {
+            self.log.borrow_mut().push(format!("{} {}", self.name, hook));
+        }
+    }
+
+    impl Scene for TestScene {
+        fn on_start(&mut self, _renderer: &mut Renderer) {
+            self.log("start");
+        }
+
+        fn on_update(&mut self, _game_status: &mut GameStatus, _renderer: &mut Renderer, input: &Input, _delta_time: f64) -> Option<SceneTransition> {
+            if input.get_key_state(Key::Space) == State::Down {
+                self.log("update with input");
+            } else {
+                self.log("update");
+            }
+            self.transitions.pop_front()
+        }
+
+        fn on_render(&mut self, _renderer: &mut Renderer, _alpha: f64) {
+            self.log("render");
+        }
+
+        fn on_destroy(&mut self) {
+            self.log("destroy");
+        }
+
+        fn on_pause(&mut self) {
+            self.log("pause");
+        }
+
+        fn on_resume(&mut self) {
+            self.log("resume");
+        }
+
+        fn renders_scenes_below(&self) -> bool {
+            self.renders_below
+        }
+
+        fn updates_scenes_below(&self) -> bool {
+            self.updates_below
+        }
+    }
+
+    struct Harness {
+        renderer: Renderer,
+        game_status: GameStatus,
+        input: Input,
+        log: Log,
+    }
+
+    impl Harness {
+        fn new() -> Harness {
+            let mut input = Input::new(320, 240, 320, 240);
+            input.set_key_state(Key::Space, State::Down);
+            Harness {
+                renderer: Renderer::new(RenderBackend::Recording(vec![])),
+                game_status: GameStatus::new(),
+                input,
+                log: Rc::new(RefCell::new(vec![]))
+            }
+        }
+
+        fn start(&mut self, scene: Box<dyn Scene>) -> SceneStack {
+            SceneStack::new(scene, &mut self.renderer)
+        }
+
+        fn update(&mut self, scene_stack: &mut SceneStack) {
+            scene_stack.update(&mut self.game_status, &mut self.renderer, &self.input, 0.1);
+        }
+
+        fn render(&mut self, scene_stack: &mut SceneStack) {
+            scene_stack.render(&mut self.renderer, 0.0);
+        }
+
+        // Log entries since the last call
+        fn take_log(&self) -> Vec<String> {
+            self.log.borrow_mut().drain(..).collect()
+        }
+    }
+
+    #[test]
+    fn push_pauses_the_top_scene_and_starts_the_new_one() {
+        let mut harness = Harness::new();
+        let pushed = TestScene::new("b", &harness.log).boxed();
+        let mut scene_stack = harness.start(TestScene::new("a", &harness.log).transition(SceneTransition::Push(pushed)).boxed());
+
+        harness.update(&mut scene_stack);
+        assert_eq!(harness.take_log(), vec!["a start", "a update with input", "a pause", "b start"]);
+
+        harness.update(&mut scene_stack);
+        harness.render(&mut scene_stack);
+        assert_eq!(harness.take_log(), vec!["b update with input", "b render"]);
+    }
+
+    #[test]
+    fn pop_destroys_the_top_scene_and_resumes_the_one_below() {
+        let mut harness = Harness::new();
+        let pushed = TestScene::new("b", &harness.log).transition(SceneTransition::Pop).boxed();
+        let mut scene_stack = harness.start(TestScene::new("a", &harness.log).transition(SceneTransition::Push(pushed)).boxed());
+        harness.update(&mut scene_stack);
+        harness.take_log();
+
+        harness.update(&mut scene_stack);
+        harness.render(&mut scene_stack);
+        assert_eq!(harness.take_log(), vec!["b update with input", "b destroy", "a resume", "a render"]);
+    }
+
+    #[test]
+    fn popping_the_last_scene_empties_the_stack() {
+        let mut harness = Harness::new();
+        let mut scene_stack = harness.start(TestScene::new("a", &harness.log).transition(SceneTransition::Pop).boxed());
+
+        harness.update(&mut scene_stack);
+        assert!(scene_stack.is_empty());
+        assert_eq!(harness.take_log(), vec!["a start", "a update with input", "a destroy"]);
+    }
+
+    #[test]
+    fn replace_destroys_the_top_scene_without_resuming_the_one_below() {
+        let mut harness = Harness::new();
+        let replacement = TestScene::new("c", &harness.log).boxed();
+        let pushed = TestScene::new("b", &harness.log).transition(SceneTransition::Replace(replacement)).boxed();
+        let mut scene_stack = harness.start(TestScene::new("a", &harness.log).transition(SceneTransition::Push(pushed)).boxed());
+        harness.update(&mut scene_stack);
+        harness.take_log();
+
+        harness.update(&mut scene_stack);
+        harness.render(&mut scene_stack);
+        assert_eq!(harness.take_log(), vec!["b update with input", "b destroy", "c start", "c render"]);
+    }
+
+    #[test]
+    fn clear_to_destroys_every_scene_from_the_top() {
+        let mut harness = Harness::new();
+        let cleared_to = TestScene::new("c", &harness.log).boxed();
+        let pushed = TestScene::new("b", &harness.log).transition(SceneTransition::ClearTo(cleared_to)).boxed();
+        let mut scene_stack = harness.start(TestScene::new("a", &harness.log).transition(SceneTransition::Push(pushed)).boxed());
+        harness.update(&mut scene_stack);
+        harness.take_log();
+
+        harness.update(&mut scene_stack);
+        assert_eq!(harness.take_log(), vec!["b update with input", "b destroy", "a destroy", "c start"]);
+
+        scene_stack.clear();
+        assert!(scene_stack.is_empty());
+        assert_eq!(harness.take_log(), vec!["c destroy"]);
+    }
+
+    #[test]
+    fn overlay_renders_scenes_below_from_the_bottom_up() {
+        let mut harness = Harness::new();
+        let overlay = TestScene::new("b", &harness.log).overlay(true, false).boxed();
+        let mut scene_stack = harness.start(TestScene::new("a", &harness.log).transition(SceneTransition::Push(overlay)).boxed());
+        harness.update(&mut scene_stack);
+        harness.take_log();
+
+        harness.update(&mut scene_stack);
+        harness.render(&mut scene_stack);
+        assert_eq!(harness.take_log(), vec!["b update with input", "a render", "b render"]);
+    }
+
+    #[test]
+    fn scenes_updated_below_an_overlay_get_no_input() {
+        let mut harness = Harness::new();
+        let overlay = TestScene::new("b", &harness.log).overlay(false, true).boxed();
+        let mut scene_stack = harness.start(TestScene::new("a", &harness.log).transition(SceneTransition::Push(overlay)).boxed());
+        harness.update(&mut scene_stack);
+        harness.take_log();
+
+        harness.update(&mut scene_stack);
+        harness.render(&mut scene_stack);
+        assert_eq!(harness.take_log(), vec!["a update", "b update with input", "b render"]);
+    }
+
+    #[test]
+    fn overlays_let_scenes_through_only_down_to_the_first_one_that_covers_them() {
+        let mut harness = Harness::new();
+        let top = TestScene::new("c", &harness.log).overlay(true, true).boxed();
+        let middle = TestScene::new("b", &harness.log).transition(SceneTransition::Push(top)).boxed();
+        let mut scene_stack = harness.start(TestScene::new("a", &harness.log).transition(SceneTransition::Push(middle)).boxed());
+        harness.update(&mut scene_stack);
+        harness.update(&mut scene_stack);
+        harness.take_log();
+
+        harness.update(&mut scene_stack);
+        harness.render(&mut scene_stack);
+        assert_eq!(harness.take_log(), vec!["b update", "c update with input", "b render", "c render"]);
+    }
+
+    #[test]
+    fn transitions_of_scenes_below_the_top_are_ignored() {
+        let mut harness = Harness::new();
+        let overlay = TestScene::new("b", &harness.log).overlay(false, true).boxed();
+        let ignored = TestScene::new("x", &harness.log).boxed();
+        let mut scene_stack = harness.start(
+            TestScene::new("a", &harness.log)
+                .transition(SceneTransition::Push(overlay))
+                .transition(SceneTransition::Replace(ignored))
+                .boxed()
+        );
+        harness.update(&mut scene_stack);
+        harness.take_log();
+
+        harness.update(&mut scene_stack);
+        harness.render(&mut scene_stack);
+        assert_eq!(harness.take_log(), vec!["a update", "b update with input", "b render"]);
+    }
 }
@@ -0,0 +1,78 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::Error;
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum ScreenshotSource {
+    // Result of the last render pass at internal render resolution
+    Framebuffer,
+    // What ends up in the window, upscaled and letterboxed to window size
+    Window,
+}
+
+pub struct Screenshot {
+    width: u32,
+    height: u32,
+    // RGBA, rows go from top to bottom
+    pixels: Vec<u8>,
+}
+
+impl Screenshot {
+    pub(crate) fn new(width: u32, height: u32, pixels: Vec<u8>) -> Screenshot {
+        Screenshot { width, height, pixels }
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_pixels(&self) -> &Vec<u8> {
+        &self.pixels
+    }
+
+    pub fn to_png_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut png_bytes: Vec<u8> = vec![];
+
+        {
+            let mut encoder = png::Encoder::new(&mut png_bytes, self.width, self.height);
+            encoder.set_color(png::ColorType::RGBA);
+            encoder.set_depth(png::BitDepth::Eight);
+
+            let mut writer = encoder.write_header().map_err(|error| Error::AssetEncode(error.to_string()))?;
+            writer.write_image_data(&self.pixels).map_err(|error| Error::AssetEncode(error.to_string()))?;
+        }
+
+        Ok(png_bytes)
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let png_bytes = self.to_png_bytes()?;
+        fs::write(&path, png_bytes)
+            .map_err(|error| Error::AssetEncode(format!("Failed to write {}: {}", path.as_ref().display(), error)))
+    }
+}
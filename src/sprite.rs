@@ -0,0 +1,58 @@
+//
+// Copyright © 2020-2024  Egidijus Lileika
+//
+// This file is part of RSFX - Game framework for PSX-feel games written in Rust
+//
+// RSFX is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 2.1 of the License, or
+// (at your option) any later version.
+//
+// RSFX is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License
+// along with RSFX. If not, see <https://www.gnu.org/licenses/>.
+//
+
+
+use glam::{Vec2, Vec4};
+
+// Rectangle in pixels
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect { x, y, width, height }
+    }
+}
+
+// Screen space sprite, drawn over the 3D scene. Position is the sprite center in framebuffer pixels, same
+// as Input::get_cursor_position, (0, 0) is the middle of the screen and y goes up.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Sprite {
+    pub position: Vec2,
+    pub size: Vec2,
+    // Part of the texture to draw, in texture pixels from the top left corner. Whole texture when None.
+    pub source_rect: Option<Rect>,
+    // Multiplies texture color, or is the color of untextured quads
+    pub tint: Vec4,
+    // Counter-clockwise around the sprite center, in radians
+    pub rotation: f32,
+    // Higher layers are drawn on top, sprites of the same layer in the order they were drawn
+    pub layer: i32,
+}
+
+impl Sprite {
+    pub fn new(position: Vec2, size: Vec2) -> Sprite {
+        Sprite { position, size, source_rect: None, tint: Vec4::ONE, rotation: 0.0, layer: 0 }
+    }
+}
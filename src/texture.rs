@@ -21,12 +21,16 @@ use std::ffi::c_void;
 use gl::{self, types::{GLuint, GLint}};
 use gl::types::GLsizei;
 
+use crate::error::Error;
 use crate::internal::byte_buffer_reader::ByteBufferReader;
+use crate::internal::render_backend::RenderBackend;
 
 pub struct Texture {
     texture_id: GLuint,
     width: GLuint,
     height: GLuint,
+    // Created in a headless run, there is no GL object behind texture_id
+    placeholder: bool,
 }
 
 #[derive(Clone, Copy)]
@@ -38,14 +42,18 @@ pub enum ImageMode {
 
 impl Texture {
     pub fn new(texture_id: GLuint, width: GLuint, height: GLuint) -> Texture {
-        Texture { texture_id, width, height }
+        Texture { texture_id, width, height, placeholder: false }
+    }
+
+    pub(crate) fn placeholder(width: GLuint, height: GLuint) -> Texture {
+        Texture { texture_id: RenderBackend::next_placeholder_id(), width, height, placeholder: true }
     }
     
-    pub fn from_png_bytes(png_bytes: &[u8]) -> Result<Texture, String> {
+    pub fn from_png_bytes(png_bytes: &[u8]) -> Result<Texture, Error> {
         let decoder = png::Decoder::new(ByteBufferReader::from(png_bytes));
         let mut reader = match decoder.read_info() {
             Ok(reader) => reader.1,
-            Err(error) => return Err(error.to_string())
+            Err(error) => return Err(Error::asset_decode(None, &error.to_string()))
         };
     
         let mut raw_image_data: Vec<u8> = vec![];
@@ -62,7 +70,7 @@ impl Texture {
         let color_size = raw_image_data.len() / (width * height);
         if color_size != 3 && color_size != 4 {
             return Err(
-                String::from(format!("Unexpected image size or it is corrupted.\nwidth: {0} \
+                Error::asset_decode(None, &format!("Unexpected image size or it is corrupted.\nwidth: {0} \
                 , height: {1}, byte_count: {2}", width, height, raw_image_data.len()))
             );
         }
@@ -71,7 +79,7 @@ impl Texture {
             4 => ImageMode::RGBA,
             _ => {
                 return Err(
-                    String::from("Unsupported image color mode. Expected RGB or RGBA color mode.")
+                    Error::asset_decode(None, "Unsupported image color mode. Expected RGB or RGBA color mode.")
                 );
             }
         };
@@ -80,6 +88,11 @@ impl Texture {
     }
     
     pub fn from_data(data: &Vec<u8>, width: GLuint, height: GLuint, mode: ImageMode) -> Texture {
+        // Headless run, nothing will sample the texture
+        if RenderBackend::is_headless() {
+            return Texture::placeholder(width, height);
+        }
+
         let texture_id = {
             let mut texture_ids = vec![0];
             unsafe {
@@ -113,7 +126,7 @@ impl Texture {
             );
         }
 
-        Texture { texture_id, width, height }
+        Texture { texture_id, width, height, placeholder: false }
     }
 
     pub fn texture_id(&self) -> GLuint {
@@ -154,6 +167,10 @@ impl Texture {
 
 impl Drop for Texture {
     fn drop(&mut self) {
+        if self.placeholder {
+            return;
+        }
+
         unsafe {
             let temp_vec = vec![self.texture_id];
             gl::DeleteTextures(temp_vec.len() as GLsizei, temp_vec.as_ptr());
@@ -17,8 +17,16 @@
 // along with RSFX. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use std::time::{SystemTime, UNIX_EPOCH};
 
-pub fn time_now() -> u128 {
-    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis()
-}
\ No newline at end of file
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+// Value of a uniform that is set by name in user shaders
+#[derive(Copy, Clone, PartialEq)]
+pub enum UniformValue {
+    Int(i32),
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    Mat4(Mat4),
+}
@@ -19,6 +19,8 @@
 
 use glam::{Vec3, Vec2};
 
+// Vertex colors are not part of it. Most meshes have none and every vertex would carry 16 unused bytes,
+// meshes that have them keep them next to the vertices through MeshData::with_colors.
 #[repr(packed(4))]
 #[derive(Copy, Clone)]
 pub struct VertexData {